@@ -0,0 +1,185 @@
+use std::fs::{
+        self,
+        File,
+};
+use std::io::{
+        self,
+        Write,
+};
+use std::path::{
+        Path,
+        PathBuf,
+};
+
+/// Replaces the contents of the file at `path` with `contents`.
+///
+/// The new contents are written to a temporary file in the same directory,
+/// flushed to disk and then renamed over `path`, so that readers, and the
+/// file left behind by a crash or a full disk, only ever hold the old or the
+/// new contents, never part of them.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        write_with(path, |file| file.write_all(contents.as_ref()))
+}
+
+/// Writes `path` as `write` does, with `fill` writing the contents to the
+/// temporary file. The temporary file is removed if anything fails.
+fn write_with(path: &Path, fill: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<()> {
+        let temp_path = temp_path(path)?;
+
+        let result = replace(path, &temp_path, fill);
+        if result.is_err() && temp_path.exists() {
+                // The error that matters is the one that stopped the write
+                fs::remove_file(&temp_path).ok();
+        }
+        result
+}
+
+fn replace(
+        path: &Path,
+        temp_path: &Path,
+        fill: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+        let mut file = File::create(temp_path)?;
+        if let Ok(metadata) = fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+        }
+        fill(&mut file)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(temp_path, path)?;
+
+        // The rename itself is only durable once the directory is flushed
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+                File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+}
+
+/// A hidden file next to `path`, such as `.TASK.md.tmp-1234` for process
+/// 1234. Leftovers of crashed writes are overwritten by the next write of the
+/// same process ID and are otherwise ignored, like every hidden file.
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+        let name = path.file_name().ok_or_else(|| {
+                io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{} is not a file path", path.display()),
+                )
+        })?;
+
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".tmp-{}", std::process::id()));
+        Ok(path.with_file_name(temp_name))
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        /// Names of the files in `dir`.
+        fn files(dir: &Path) -> io::Result<Vec<String>> {
+                let mut names: Vec<String> = fs::read_dir(dir)?
+                        .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                        .collect::<io::Result<_>>()?;
+                names.sort();
+                Ok(names)
+        }
+
+        #[test]
+        fn replaces_contents_without_leaving_files_behind() -> io::Result<()> {
+                let dir = tempfile::tempdir()?;
+                let path = dir.path().join("TASK.md");
+                fs::write(&path, "old")?;
+
+                write(&path, "new")?;
+
+                assert_eq!(fs::read_to_string(&path)?, "new");
+                assert_eq!(files(dir.path())?, ["TASK.md"]);
+                Ok(())
+        }
+
+        #[test]
+        fn creates_missing_files() -> io::Result<()> {
+                let dir = tempfile::tempdir()?;
+                let path = dir.path().join(".config");
+
+                write(&path, "timezone = \"+00:00\"\n")?;
+
+                assert_eq!(fs::read_to_string(&path)?, "timezone = \"+00:00\"\n");
+                assert_eq!(files(dir.path())?, [".config"]);
+                Ok(())
+        }
+
+        #[test]
+        fn keeps_old_contents_when_interrupted_mid_write() -> io::Result<()> {
+                let dir = tempfile::tempdir()?;
+                let path = dir.path().join("TASK.md");
+                fs::write(&path, "---\nstatus: OPEN\n---\n\n# Old\n")?;
+
+                // Half of the file is written before the disk fills up
+                let result = write_with(&path, |file| {
+                        file.write_all(b"---\nstat")?;
+                        Err(io::Error::new(
+                                io::ErrorKind::StorageFull,
+                                "No space left on device",
+                        ))
+                });
+
+                assert_eq!(
+                        result.map_err(|e| e.kind()),
+                        Err(io::ErrorKind::StorageFull)
+                );
+                assert_eq!(
+                        fs::read_to_string(&path)?,
+                        "---\nstatus: OPEN\n---\n\n# Old\n"
+                );
+                assert_eq!(files(dir.path())?, ["TASK.md"]);
+                Ok(())
+        }
+
+        #[test]
+        fn recovers_from_a_crash_before_the_rename() -> io::Result<()> {
+                let dir = tempfile::tempdir()?;
+                let path = dir.path().join("TASK.md");
+                fs::write(&path, "old")?;
+
+                // A process that died mid-write leaves its temporary file
+                let leftover = temp_path(&path)?;
+                fs::write(&leftover, "ne")?;
+                assert_eq!(fs::read_to_string(&path)?, "old");
+
+                write(&path, "new")?;
+
+                assert_eq!(fs::read_to_string(&path)?, "new");
+                assert_eq!(files(dir.path())?, ["TASK.md"]);
+                Ok(())
+        }
+
+        #[test]
+        fn fails_without_creating_anything_in_missing_directories() -> io::Result<()> {
+                let dir = tempfile::tempdir()?;
+                let path = dir.path().join("missing").join("TASK.md");
+
+                assert!(write(&path, "new").is_err());
+                assert!(files(dir.path())?.is_empty());
+                Ok(())
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn keeps_permissions() -> io::Result<()> {
+                use std::os::unix::fs::PermissionsExt;
+
+                let dir = tempfile::tempdir()?;
+                let path = dir.path().join("TASK.md");
+                fs::write(&path, "old")?;
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+                write(&path, "new")?;
+
+                assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+                Ok(())
+        }
+}
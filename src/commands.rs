@@ -1,67 +1,319 @@
-use std::env;
+use std::collections::{
+        HashMap,
+        HashSet,
+};
 use std::fmt::Display;
+use std::path::Path;
 use std::process::Command;
 use std::str::FromStr;
+use std::{
+        env,
+        fs,
+        io,
+};
 
 use lexopt::prelude::*;
 
-use crate::store::Store;
-
+use crate::config::{
+        self,
+        Config,
+};
+use crate::datetime::{
+        self,
+        DateTime,
+        parse_datetime,
+};
+use crate::format::{
+        Entry,
+        Format,
+};
+use crate::query::{
+        self,
+        Expr,
+};
+use crate::sort::{
+        self,
+        DEFAULT_SORT,
+};
+use crate::store::{
+        DEFAULT_PRIORITY,
+        Store,
+        find_task,
+        huid_of,
+        parent_of,
+        parse_huid,
+        same_file,
+        short_id,
+};
+use crate::tz::TimeZone;
+use crate::workflow::Workflow;
+use crate::{
+        atomic,
+        graph,
+};
+
+/// The state of a task, one of those of the store's workflow.
 #[derive(Debug, PartialEq)]
-pub enum Status {
-        Open,
-        Closed,
+pub struct Status(String);
+
+impl Status {
+        pub fn as_str(&self) -> &str {
+                &self.0
+        }
 }
 
 impl Display for Status {
-        #[rustfmt::skip]
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                match self {
-                        Status::Open   => write!(f, "OPEN"),
-                        Status::Closed => write!(f, "CLOSED"),
-                }
+                write!(f, "{}", self.0)
         }
 }
 
 impl FromStr for Status {
         type Err = lexopt::Error;
 
-        #[rustfmt::skip]
+        /// Accepts any single word. Whether it belongs to the workflow is
+        /// checked when the store is read.
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-                match s.to_uppercase().as_str() {
-                        "OPEN"   => Ok(Status::Open),
-                        "CLOSED" => Ok(Status::Closed),
-                        &_       => unreachable!(),
+                if s.is_empty() || s.contains(char::is_whitespace) {
+                        return Err(lexopt::Error::Custom(
+                                format!("Invalid status: '{s}'").into(),
+                        ));
                 }
+                Ok(Status(s.to_string()))
         }
 }
 
 #[derive(Debug)]
 pub struct Task {
-        pub tags:     Vec<String>,
-        pub title:    String,
-        pub status:   Status,
-        pub priority: u8,
+        pub tags:       Vec<String>,
+        pub title:      String,
+        pub status:     Status,
+        pub priority:   u8,
+        /// When the task was created, from the `created:` front matter key.
+        pub created:    Option<i64>,
+        /// When the task was closed, from the `closed:` front matter key.
+        pub closed:     Option<i64>,
+        /// When the task is due, from the `due:` front matter key.
+        pub due:        Option<i64>,
+        /// HUIDs of the tasks this one waits for, from the `depends:` front
+        /// matter key.
+        pub depends:    Vec<String>,
+        /// Everything below the title line.
+        pub body:       String,
+        /// Line of the `# ` title in the task file.
+        pub title_line: usize,
+        /// Line of each front matter key in the task file.
+        pub key_lines:  Vec<(String, usize)>,
+}
+
+impl Task {
+        /// Returns the line of front matter `key`, or of the title for `title`.
+        pub fn line_of(&self, key: &str) -> Option<usize> {
+                if key == "title" {
+                        return Some(self.title_line);
+                }
+                self.key_lines
+                        .iter()
+                        .find(|(k, _)| k == key)
+                        .map(|(_, n)| *n)
+        }
 }
 
 #[derive(Default)]
 pub struct ListArgs {
-        pub show_closed: bool,
-        pub show_global: bool,
+        pub show_closed:  bool,
+        pub show_global:  bool,
+        /// `+TAG`: every one of these tags is required.
+        pub with_tags:    Vec<String>,
+        /// `-TAG`: none of these tags is allowed.
+        pub without_tags: Vec<String>,
+        /// `-t A,B`: at least one tag of each group is required.
+        pub any_tags:     Vec<Vec<String>>,
+        /// `-q EXPR`: a query the task must match.
+        pub query:        Option<Expr>,
+        /// `-f FORMAT`: resolved against the store once it is known.
+        pub format:       Option<String>,
+        /// `--line KEY`: the line of the task file the output points at.
+        pub line:         Option<String>,
+        /// `--sort KEYS`: falls back to the store configuration.
+        pub sort:         Option<String>,
+        /// `--age`: adds the age of tasks to the quickfix output.
+        pub show_age:     bool,
+        /// `--tree`: subtasks are listed under their parent.
+        pub tree:         bool,
+        /// `--ready`: only open tasks whose dependencies are all closed.
+        pub ready:        bool,
+        /// `--blocked`: only tasks with a dependency that is still open.
+        pub blocked:      bool,
+        /// `--overdue`: only tasks whose due time has passed.
+        pub overdue:      bool,
+        /// `--due-before WHEN`: resolved once the store timezone is known.
+        pub due_before:   Option<String>,
+}
+
+impl ListArgs {
+        /// Checks the tag selectors and query against the task in `folder_name`,
+        /// in a store in `timezone`.
+        pub fn matches(&self, folder_name: &str, task: &Task, timezone: &TimeZone) -> bool {
+                let has = |tag: &String| task.tags.contains(tag);
+
+                self.with_tags.iter().all(has)
+                        && !self.without_tags.iter().any(has)
+                        && self.any_tags.iter().all(|group| group.iter().any(has))
+                        && self.query.as_ref().is_none_or(|query| {
+                                let created = created_at(folder_name, task, timezone)
+                                        .map(|created| timezone.local(created));
+                                query.matches(task, created.as_ref())
+                        })
+        }
+
+        /// Resolves `--overdue` and `--due-before` to the time before which
+        /// tasks must be due, if any.
+        pub fn due_limit(
+                &self,
+                now: i64,
+                timezone: &TimeZone,
+        ) -> Result<Option<i64>, lexopt::Error> {
+                let due_before = self
+                        .due_before
+                        .as_deref()
+                        .map(|when| {
+                                parse_datetime(when, now, timezone)
+                                        .ok_or_else(|| invalid_datetime("date", when))
+                        })
+                        .transpose()?;
+                Ok(match (self.overdue, due_before) {
+                        (true, Some(before)) => Some(before.min(now)),
+                        (true, None) => Some(now),
+                        (false, before) => before,
+                })
+        }
+
+        /// Returns the tasks that are shown, in the order of `tasks`.
+        pub fn select<'a>(
+                &self,
+                tasks: &'a [(String, Task)],
+                workflow: &Workflow,
+                is_closed_by_huid: &HashMap<&str, bool>,
+                due_limit: Option<i64>,
+                timezone: &TimeZone,
+        ) -> Vec<&'a (String, Task)> {
+                tasks.iter()
+                        .filter(|(folder_name, task)| {
+                                let is_closed = workflow.is_closed(task.status.as_str());
+                                let is_blocked =
+                                        !open_dependencies(task, is_closed_by_huid).is_empty();
+                                (self.show_closed || !is_closed)
+                                        && (!self.ready || (!is_closed && !is_blocked))
+                                        && (!self.blocked || is_blocked)
+                                        && self.matches(folder_name, task, timezone)
+                                        && due_limit.is_none_or(|limit| {
+                                                task.due.is_some_and(|due| due < limit)
+                                        })
+                        })
+                        .collect()
+        }
+}
+
+/// Long flags that select which tasks `list` and `graph` show, besides those
+/// of `handle_list_arg`. `-t` and `-q` stand for `--tag` and `--query`.
+const SELECTOR_FLAGS: &[&str] = &["tag", "query", "ready", "blocked", "overdue", "due-before"];
+
+/// Front matter keys that tafsk reads. Tasks may have others of their own.
+const FRONT_MATTER_KEYS: &[&str] = &[
+        "status", "priority", "tags", "created", "closed", "due", "depends",
+];
+
+/// Handles one of `SELECTOR_FLAGS`, reading its value from `parser`.
+fn handle_selector_arg(
+        parser: &mut lexopt::Parser,
+        args: &mut ListArgs,
+        flag: &str,
+) -> Result<(), lexopt::Error> {
+        match flag {
+                "tag" => {
+                        let group: Vec<String> = parser
+                                .value()?
+                                .string()?
+                                .split(',')
+                                .map(|t| t.trim().to_string())
+                                .filter(|t| !t.is_empty())
+                                .collect();
+                        if !group.is_empty() {
+                                args.any_tags.push(group);
+                        }
+                },
+                "query" => args.query = Some(parse_query(parser)?),
+                "ready" => args.ready = true,
+                "blocked" => args.blocked = true,
+                "overdue" => args.overdue = true,
+                "due-before" => args.due_before = Some(parser.value()?.string()?),
+                _ => {},
+        }
+        Ok(())
+}
+
+/// When the task in `folder_name` was created: its `created:` key, or else the
+/// time of its HUID in `timezone`, for tasks created before the key was.
+pub fn created_at(folder_name: &str, task: &Task, timezone: &TimeZone) -> Option<i64> {
+        task.created.or_else(|| {
+                parse_huid(huid_of(folder_name))
+                        .ok()
+                        .map(|dt| timezone.timestamp(&dt))
+        })
+}
+
+/// Tells, for the HUID of every task, whether the task is closed.
+fn closed_by_huid<'a>(tasks: &'a [(String, Task)], workflow: &Workflow) -> HashMap<&'a str, bool> {
+        tasks.iter()
+                .map(|(path, task)| (huid_of(path), workflow.is_closed(task.status.as_str())))
+                .collect()
 }
 
 pub fn handle_list_arg(args: &mut ListArgs, arg: lexopt::Arg) -> Result<(), lexopt::Error> {
         match arg {
                 Short('c') | Long("closed") => args.show_closed = true,
                 Short('g') | Long("global") => args.show_global = true,
+                Value(ref val) if val.to_str().is_some_and(|v| v.starts_with('+')) => {
+                        let tag = val.to_string_lossy();
+                        if tag.len() > 1 {
+                                args.with_tags.push(tag[1..].to_string());
+                        }
+                },
+                // Only reached after `--`, for tags that read as flags
+                Value(ref val)
+                        if val.to_str()
+                                .is_some_and(|v| v.len() > 1 && v.starts_with('-')) =>
+                {
+                        args.without_tags
+                                .push(val.to_string_lossy()[1..].to_string());
+                },
                 _ => return Err(arg.unexpected()),
         }
         Ok(())
 }
 
+/// The error for a time given on the command line that couldn't be parsed.
+fn invalid_datetime(what: &str, input: &str) -> lexopt::Error {
+        lexopt::Error::Custom(
+                format!(
+                        "Invalid {} '{}'. Expected a date such as 2026-11-01, 17:00, tomorrow, fri or in 3d",
+                        what, input
+                )
+                .into(),
+        )
+}
+
+/// Parses the value of `-q/--query`.
+fn parse_query(parser: &mut lexopt::Parser) -> Result<Expr, lexopt::Error> {
+        query::parse(&parser.value()?.string()?).map_err(|e| lexopt::Error::Custom(e.into()))
+}
+
 impl Task {
         pub fn add(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
-                let mut priority: u8 = 10;
+                let mut priority: Option<u8> = None;
+                let mut due = None;
+                let mut parent = None;
                 let mut edit_mode = false;
                 let mut args = ListArgs::default();
                 let mut payload: Vec<String> = Vec::new();
@@ -77,14 +329,18 @@ impl Task {
                                         println!();
                                         println!("FLAGS:");
                                         println!("    -h, --help           Prints help information");
-                                        println!("    -p, --priority <N>   Set priority (0-255, default: 10)");
+                                        println!("    -p, --priority <N>   Set priority (0-255, default: 10 or default_priority)");
+                                        println!("    -d, --due <WHEN>     Set the due date (e.g. 2026-11-01, 17:00, tomorrow, in 3d)");
+                                        println!("        --parent <ID>    Create a subtask of another task");
                                         println!("    -e, --edit           Open editor after creating");
                                         println!("    -g, --global         Add tasks to the default store");
                                         return Ok(());
                                 },
                                 Short('p') | Long("priority") => {
-                                        priority = parser.value()?.parse()?
+                                        priority = Some(parser.value()?.parse()?)
                                 },
+                                Short('d') | Long("due") => due = Some(parser.value()?.string()?),
+                                Long("parent") => parent = Some(parser.value()?.string()?),
                                 Short('e') | Long("edit") => edit_mode = true,
                                 Short('g') | Long("global") => args.show_global = true,
                                 Value(val) => payload.push(val.string()?),
@@ -111,121 +367,799 @@ impl Task {
                         ));
                 }
 
+                let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let timezone = store
+                        .timezone()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let workflow = store
+                        .workflow()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let priority = match priority {
+                        Some(priority) => priority,
+                        None => store
+                                .default_priority()
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?,
+                };
+                // A due date that can't be read back would hide the task from `list`
+                let due = due
+                        .map(|when| {
+                                parse_datetime(&when, datetime::now(), &timezone)
+                                        .filter(|&timestamp| {
+                                                let written = datetime::format_rfc3339(
+                                                        timestamp, &timezone,
+                                                );
+                                                DateTime::parse_rfc3339(&written) == Some(timestamp)
+                                        })
+                                        .ok_or_else(|| invalid_datetime("due date", &when))
+                        })
+                        .transpose()?;
+
                 let task = Task {
                         title: title.join(" "),
                         tags,
-                        status: Status::Open,
+                        status: Status(workflow.initial().to_string()),
                         priority,
+                        created: None,
+                        closed: None,
+                        due,
+                        depends: Vec::new(),
+                        body: String::new(),
+                        title_line: 0,
+                        key_lines: Vec::new(),
+                };
+
+                let parent = match parent {
+                        Some(parent_id) => {
+                                let mut tasks = store
+                                        .list_tasks()
+                                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                                let idx = find_task(&tasks, &parent_id)
+                                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                                Some(tasks.swap_remove(idx).0)
+                        },
+                        None => None,
+                };
+
+                let id = store
+                        .create_task(&task.render(&timezone), parent.as_deref())
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                let dir = match &parent {
+                        Some(parent) => store.root.join(parent),
+                        None => store.root.clone(),
+                };
+                println!("Created task {} at {}", huid_of(&id), dir.display());
+                drop(lock);
+
+                if edit_mode {
+                        let path = store.root.join(&id).join("TASK.md");
+                        let content = fs::read_to_string(&path)
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                        edit_until_valid(&store, &path, &content)?;
+                }
+
+                Ok(())
+        }
+
+        pub fn done(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut ids = Vec::new();
+                let mut args = ListArgs::default();
+                while let Some(arg) = parser.next()? {
+                        match arg {
+                                Value(val) => ids.push(val.string()?),
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-done");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk done [FLAGS] [ID]...");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help           Prints help information");
+                                        println!("    -g, --global         Mark tasks from the default store");
+                                        println!("    -q, --query <EXPR>   Also mark every task matching EXPR");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    <ID>...              One or more task IDs (HUID or unique prefix) to mark as closed");
+                                        return Ok(());
+                                },
+                                Short('g') | Long("global") => args.show_global = true,
+                                Short('q') | Long("query") => {
+                                        args.query = Some(parse_query(parser)?)
+                                },
+                                _ => return Err(arg.unexpected()),
+                        }
+                }
+
+                if ids.is_empty() && args.query.is_none() {
+                        println!("Usage: done <ID>...");
+                        return Ok(());
+                }
+
+                set_status(&args, &ids, &Change::Close)
+        }
+
+        pub fn reopen(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut ids = Vec::new();
+                let mut args = ListArgs::default();
+                while let Some(arg) = parser.next()? {
+                        match arg {
+                                Value(val) => ids.push(val.string()?),
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-reopen");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk reopen [FLAGS] [ID]...");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help           Prints help information");
+                                        println!("    -g, --global         Reopen tasks from the default store");
+                                        println!("    -q, --query <EXPR>   Also mark every task matching EXPR");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    <ID>...              One or more task IDs (HUID or unique prefix) to mark as open");
+                                        return Ok(());
+                                },
+                                Short('g') | Long("global") => args.show_global = true,
+                                Short('q') | Long("query") => {
+                                        args.query = Some(parse_query(parser)?)
+                                },
+                                _ => return Err(arg.unexpected()),
+                        }
+                }
+
+                if ids.is_empty() && args.query.is_none() {
+                        println!("Usage: reopen <ID>...");
+                        return Ok(());
+                }
+
+                set_status(&args, &ids, &Change::Reopen)
+        }
+
+        pub fn status(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut values = Vec::new();
+                let mut force = false;
+                let mut args = ListArgs::default();
+                while let Some(arg) = parser.next()? {
+                        match arg {
+                                Value(val) => values.push(val.string()?),
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-status");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk status [FLAGS] [ID]... <STATE>");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help           Prints help information");
+                                        println!("    -g, --global         Move tasks from the default store");
+                                        println!("    -q, --query <EXPR>   Also move every task matching EXPR");
+                                        println!("        --force          Allow moves that the workflow doesn't");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    <ID>...              One or more task IDs (HUID or unique prefix) to move");
+                                        println!("    <STATE>              A state of the store's workflow");
+                                        return Ok(());
+                                },
+                                Short('g') | Long("global") => args.show_global = true,
+                                Short('q') | Long("query") => {
+                                        args.query = Some(parse_query(parser)?)
+                                },
+                                Long("force") => force = true,
+                                _ => return Err(arg.unexpected()),
+                        }
+                }
+
+                let Some(state) = values.pop() else {
+                        println!("Usage: status <ID>... <STATE>");
+                        return Ok(());
+                };
+                if values.is_empty() && args.query.is_none() {
+                        println!("Usage: status <ID>... <STATE>");
+                        return Ok(());
+                }
+
+                set_status(&args, &values, &Change::Move { state, force })
+        }
+
+        pub fn edit(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut id = None;
+                let mut args = ListArgs::default();
+                while let Some(arg) = parser.next()? {
+                        match arg {
+                                Value(val) if id.is_none() => id = Some(val.string()?),
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-edit");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk edit [FLAGS] <ID>");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help       Prints help information");
+                                        println!("    -g, --global     Edit a task from the default store");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    <ID>             Task ID (HUID or unique prefix) to open in the editor");
+                                        return Ok(());
+                                },
+                                Short('g') | Long("global") => args.show_global = true,
+                                _ => return Err(arg.unexpected()),
+                        }
+                }
+
+                let Some(id) = id else {
+                        println!("Usage: edit <ID>");
+                        return Ok(());
+                };
+
+                let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let tasks = store
+                        .list_tasks()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let idx = find_task(&tasks, &id).map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                let path = store.root.join(&tasks[idx].0).join("TASK.md");
+                let content =
+                        fs::read_to_string(&path).map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                edit_until_valid(&store, &path, &content)
+        }
+
+        pub fn modify(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut ids = Vec::new();
+                let mut args = ListArgs::default();
+                let mut priority: Option<u8> = None;
+                let mut title: Option<String> = None;
+                let mut add_tags: Vec<String> = Vec::new();
+                let mut remove_tags: Vec<String> = Vec::new();
+
+                loop {
+                        if let Some(tag) = take_tag_removal(parser, "hg", "pq") {
+                                remove_tags.push(tag);
+                                continue;
+                        }
+
+                        let Some(arg) = parser.next()? else {
+                                break;
+                        };
+
+                        match arg {
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-modify");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk modify [FLAGS] <ID>... [+TAG]... [-TAG]...");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help           Prints help information");
+                                        println!("    -p, --priority <N>   Set priority (0-255)");
+                                        println!("        --title <TEXT>   Replace the title");
+                                        println!("    -g, --global         Modify tasks from the default store");
+                                        println!("    -q, --query <EXPR>   Also modify every task matching EXPR");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    <ID>...              One or more task IDs (HUID or unique prefix) to modify");
+                                        println!("    +TAG                 Add a tag");
+                                        println!("    -TAG                 Remove a tag");
+                                        return Ok(());
+                                },
+                                Short('p') | Long("priority") => {
+                                        priority = Some(parser.value()?.parse()?)
+                                },
+                                Long("title") => title = Some(parser.value()?.string()?),
+                                Short('g') | Long("global") => args.show_global = true,
+                                Short('q') | Long("query") => {
+                                        args.query = Some(parse_query(parser)?)
+                                },
+                                Value(val) => {
+                                        let val = val.string()?;
+                                        match val.strip_prefix('+') {
+                                                Some(tag) if !tag.is_empty() => {
+                                                        add_tags.push(tag.to_string())
+                                                },
+                                                Some(_) => {},
+                                                // Only after `--`, for tags that read as flags
+                                                None => match val.strip_prefix('-') {
+                                                        Some(tag) if !tag.is_empty() => {
+                                                                remove_tags.push(tag.to_string())
+                                                        },
+                                                        _ => ids.push(val),
+                                                },
+                                        }
+                                },
+                                _ => return Err(arg.unexpected()),
+                        }
+                }
+
+                if ids.is_empty() && args.query.is_none() {
+                        println!("Usage: modify <ID>... [-p N] [+TAG] [-TAG] [--title TEXT]");
+                        return Ok(());
+                }
+
+                let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let _lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let tasks = store
+                        .list_tasks()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let timezone = store
+                        .timezone()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                for idx in select_tasks(&tasks, &ids, &args, &timezone)? {
+                        let (folder_name, task) = &tasks[idx];
+
+                        let mut tags = task.tags.clone();
+                        tags.retain(|t| !remove_tags.contains(t));
+                        for tag in &add_tags {
+                                if !tags.contains(tag) {
+                                        tags.push(tag.clone());
+                                }
+                        }
+
+                        let mut changes = Vec::new();
+                        let path = store.root.join(folder_name).join("TASK.md");
+                        let mut content = fs::read_to_string(&path)
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                        if let Some(p) = priority
+                                && p != task.priority
+                        {
+                                content = set_front_matter(&content, "priority", &p.to_string());
+                                changes.push(format!("priority: {} -> {}", task.priority, p));
+                        }
+                        if tags != task.tags {
+                                content = set_front_matter(
+                                        &content,
+                                        "tags",
+                                        &format!("[{}]", tags.join(", ")),
+                                );
+                                changes.push(format!(
+                                        "tags: [{}] -> [{}]",
+                                        task.tags.join(", "),
+                                        tags.join(", ")
+                                ));
+                        }
+                        if let Some(t) = &title
+                                && *t != task.title
+                        {
+                                content = set_title(&content, t);
+                                changes.push(format!("title: '{}' -> '{}'", task.title, t));
+                        }
+
+                        if changes.is_empty() {
+                                println!(
+                                        "Task [{}] '{}' is unchanged.",
+                                        short_id(folder_name),
+                                        task.title
+                                );
+                                continue;
+                        }
+
+                        atomic::write(&path, content)
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                        println!(
+                                "Modified task [{}] '{}':",
+                                short_id(folder_name),
+                                task.title
+                        );
+                        for change in changes {
+                                println!("    {}", change);
+                        }
+                }
+                Ok(())
+        }
+
+        pub fn config(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut values = Vec::new();
+                let mut scope = Scope::Effective;
+                let mut args = ListArgs::default();
+                while let Some(arg) = parser.next()? {
+                        match arg {
+                                Value(val) => values.push(val.string()?),
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-config");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk config [FLAGS] list");
+                                        println!("    tafsk config [FLAGS] get <KEY>");
+                                        println!("    tafsk config [FLAGS] set <KEY> <VALUE>");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help       Prints help information");
+                                        println!("    -g, --global     Use the default store");
+                                        println!("        --store      Only read or write the store's .config (default for set)");
+                                        println!("        --user       Only read or write the user configuration");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    <KEY>            timezone, default_priority, editor, id_format, format, sort,");
+                                        println!("                     workflow, store or format.NAME");
+                                        return Ok(());
+                                },
+                                Short('g') | Long("global") => args.show_global = true,
+                                Long("store") => scope = Scope::Store,
+                                Long("user") => scope = Scope::User,
+                                _ => return Err(arg.unexpected()),
+                        }
+                }
+
+                let store = match scope {
+                        Scope::User => None,
+                        Scope::Store => Some(Store::new(args.show_global)
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?),
+                        // Outside of a store, the user configuration is all there is
+                        Scope::Effective => Store::new(args.show_global).ok(),
+                };
+
+                let values: Vec<&str> = values.iter().map(String::as_str).collect();
+                match values.as_slice() {
+                        ["list"] => {
+                                for (key, value, source) in settings(store.as_ref(), scope)
+                                        .map_err(|e| lexopt::Error::Custom(e.into()))?
+                                {
+                                        match scope {
+                                                Scope::Effective => println!(
+                                                        "{}  # {}",
+                                                        config::setting_line(&key, &value),
+                                                        source
+                                                ),
+                                                Scope::Store | Scope::User => {
+                                                        println!(
+                                                                "{}",
+                                                                config::setting_line(&key, &value)
+                                                        )
+                                                },
+                                        }
+                                }
+                                Ok(())
+                        },
+                        ["get", key] => {
+                                let value = settings(store.as_ref(), scope)
+                                        .map_err(|e| lexopt::Error::Custom(e.into()))?
+                                        .into_iter()
+                                        .find(|(k, ..)| k == key)
+                                        .map(|(_, value, _)| value)
+                                        .ok_or_else(|| {
+                                                lexopt::Error::Custom(
+                                                        format!("Setting '{}' is not set", key)
+                                                                .into(),
+                                                )
+                                        })?;
+                                println!("{}", value);
+                                Ok(())
+                        },
+                        ["set", key, value] => {
+                                config::validate(key, value)
+                                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                                let path = match (scope, store) {
+                                        (Scope::User, _) => {
+                                                config::set_user(key, value).map_err(|e| {
+                                                        lexopt::Error::Custom(e.into())
+                                                })?;
+                                                config::user_path().unwrap_or_default()
+                                        },
+                                        _ if *key == "store" => {
+                                                return Err(lexopt::Error::Custom(
+                                                        "The store setting belongs in the user configuration. Use --user".into(),
+                                                ));
+                                        },
+                                        (_, Some(store)) => {
+                                                let _lock = store.lock().map_err(|e| {
+                                                        lexopt::Error::Custom(e.into())
+                                                })?;
+                                                store.set_setting(key, value).map_err(|e| {
+                                                        lexopt::Error::Custom(e.into())
+                                                })?;
+                                                store.root.join(".config")
+                                        },
+                                        (_, None) => {
+                                                return Err(lexopt::Error::Custom(
+                                                        "No task store found. Run 'tafsk init' to create one, or use --user.".into(),
+                                                ));
+                                        },
+                                };
+
+                                println!(
+                                        "Set {} in {}",
+                                        config::setting_line(key, value),
+                                        path.display()
+                                );
+                                Ok(())
+                        },
+                        _ => {
+                                println!("Usage: config list | get <KEY> | set <KEY> <VALUE>");
+                                Ok(())
+                        },
+                }
+        }
+
+        pub fn mv(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut id = None;
+                let mut parent = None;
+                let mut to_global = None;
+                let mut args = ListArgs::default();
+                while let Some(arg) = parser.next()? {
+                        match arg {
+                                Value(val) if id.is_none() => id = Some(val.string()?),
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-mv");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk mv [FLAGS] <ID>");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help           Prints help information");
+                                        println!("    -g, --global         Move a task from the default store");
+                                        println!("        --parent <ID>    Make the task a subtask of another task");
+                                        println!("        --to-global      Move the task to the default store");
+                                        println!("        --to-local       Move the task to the local store");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    <ID>                 Task ID (HUID or unique prefix) to move");
+                                        return Ok(());
+                                },
+                                Short('g') | Long("global") => args.show_global = true,
+                                Long("parent") => parent = Some(parser.value()?.string()?),
+                                Long("to-global") => to_global = Some(true),
+                                Long("to-local") => to_global = Some(false),
+                                _ => return Err(arg.unexpected()),
+                        }
+                }
+
+                let Some(id) = id else {
+                        println!("Usage: mv <ID> [--parent <ID>] [--to-global | --to-local]");
+                        return Ok(());
                 };
+                if parent.is_none() && to_global.is_none() {
+                        println!("Usage: mv <ID> [--parent <ID>] [--to-global | --to-local]");
+                        return Ok(());
+                }
 
                 let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
-                let content = task.render();
-                let id = store
-                        .create_task(&content)
+                let _lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let tasks = store
+                        .list_tasks()
                         .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let idx = find_task(&tasks, &id).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let (task_path, task) = &tasks[idx];
+
+                let target = match to_global {
+                        Some(true) => Store::new(true).map_err(|e| lexopt::Error::Custom(e.into()))?,
+                        Some(false) => Store::local()
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?
+                                .ok_or_else(|| {
+                                        lexopt::Error::Custom(
+                                                "No local task store found. Run 'tafsk init' to create one.".into(),
+                                        )
+                                })?,
+                        None => Store::from_path(store.root.clone()),
+                };
+                let _target_lock = if same_file(&store.root, &target.root) {
+                        None
+                } else {
+                        Some(target.lock().map_err(|e| lexopt::Error::Custom(e.into()))?)
+                };
 
-                println!("Created task {} at {}", id, store.root.display());
-
-                if edit_mode {
-                        let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
-                        let path = store.root.join(&id).join("TASK.md");
-
-                        let status = Command::new(&editor)
-                                .arg(&path)
-                                .status()
-                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let parent = match parent {
+                        Some(parent_id) => {
+                                let mut target_tasks = target
+                                        .list_tasks()
+                                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                                let parent_idx = find_task(&target_tasks, &parent_id)
+                                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                                Some(target_tasks.swap_remove(parent_idx).0)
+                        },
+                        None => None,
+                };
 
-                        if !status.success() {
-                                return Err(lexopt::Error::Custom(
-                                        format!("Editor exited with error: {}", status).into(),
-                                ));
-                        }
-                }
+                let new_path = store
+                        .move_task(task_path, &target, parent.as_deref())
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
 
+                println!(
+                        "Moved task [{}] '{}' to {}",
+                        short_id(task_path),
+                        task.title,
+                        target.root.join(new_path).join("TASK.md").display()
+                );
                 Ok(())
         }
 
-        pub fn done(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
-                let mut ids = Vec::new();
+        pub fn depend(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut id = None;
+                let mut on = Vec::new();
                 let mut args = ListArgs::default();
                 while let Some(arg) = parser.next()? {
                         match arg {
-                                Value(val) => {
-                                        let id: usize = val.parse()?;
-                                        ids.push(id);
-                                },
+                                Value(val) if id.is_none() => id = Some(val.string()?),
                                 #[rustfmt::skip]
                                 Short('h') | Long("help") => {
-                                        println!("tafsk-done");
+                                        println!("tafsk-depend");
                                         println!();
                                         println!("USAGE:");
-                                        println!("    tafsk done [ID]...");
+                                        println!("    tafsk depend [FLAGS] <ID> --on <ID>...");
                                         println!();
                                         println!("FLAGS:");
                                         println!("    -h, --help       Prints help information");
-                                        println!("    -g, --global     Mark tasks from the default store");
+                                        println!("    -g, --global     Link tasks of the default store");
+                                        println!("        --on <ID>    A task that must be closed first, may be repeated");
                                         println!();
                                         println!("ARGS:");
-                                        println!("    <ID>...          One or more task IDs to mark as closed");
+                                        println!("    <ID>             Task ID (HUID or unique prefix) that waits");
                                         return Ok(());
                                 },
                                 Short('g') | Long("global") => args.show_global = true,
+                                Long("on") => on.push(parser.value()?.string()?),
                                 _ => return Err(arg.unexpected()),
                         }
                 }
 
-                if ids.is_empty() {
-                        println!("Usage: done <ID>...");
+                let Some(id) = id else {
+                        println!("Usage: depend <ID> --on <ID>...");
+                        return Ok(());
+                };
+                if on.is_empty() {
+                        println!("Usage: depend <ID> --on <ID>...");
                         return Ok(());
                 }
 
                 let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let _lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
                 let tasks = store
                         .list_tasks()
                         .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let idx = find_task(&tasks, &id).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let (folder_name, task) = &tasks[idx];
+                let huid = huid_of(folder_name);
+
+                let mut graph: HashMap<&str, Vec<&str>> = tasks
+                        .iter()
+                        .map(|(path, t)| {
+                                (
+                                        huid_of(path),
+                                        t.depends.iter().map(String::as_str).collect(),
+                                )
+                        })
+                        .collect();
 
-                for id in ids {
-                        if id == 0 || id > tasks.len() {
-                                return Err(lexopt::Error::Custom(
-                                        "No matches.".to_string().into(),
-                                ));
+                // Every dependency is checked before the task is written
+                let mut added = Vec::new();
+                for dependency_id in &on {
+                        let dependency_idx = find_task(&tasks, dependency_id)
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                        let (dependency_path, dependency) = &tasks[dependency_idx];
+                        let dependency_huid = huid_of(dependency_path);
+
+                        if graph.get(huid)
+                                .is_some_and(|deps| deps.contains(&dependency_huid))
+                        {
+                                println!(
+                                        "Task [{}] '{}' already depends on [{}] '{}'.",
+                                        short_id(folder_name),
+                                        task.title,
+                                        short_id(dependency_path),
+                                        dependency.title
+                                );
+                                continue;
                         }
-
-                        let (folder_name, task) = &tasks[id - 1];
-
-                        if task.status == Status::Closed {
+                        if let Some(chain) = dependency_chain(&graph, dependency_huid, huid) {
+                                let chain: Vec<&str> = chain.into_iter().map(short_id).collect();
                                 return Err(lexopt::Error::Custom(
                                         format!(
-                                                "Task [{}] '{}' is already CLOSED.",
-                                                id, task.title
+                                                "Can't make [{}] depend on [{}]: it would close the cycle {} -> {}",
+                                                short_id(huid),
+                                                short_id(dependency_huid),
+                                                short_id(huid),
+                                                chain.join(" -> ")
                                         )
                                         .into(),
                                 ));
                         }
 
-                        let path = store.root.join(folder_name).join("TASK.md");
-                        let content = std::fs::read_to_string(&path)
-                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                        graph.entry(huid).or_default().push(dependency_huid);
+                        added.push((dependency_path, dependency));
+                }
+                if added.is_empty() {
+                        return Ok(());
+                }
 
-                        let new_lines: Vec<String> = content
-                                .lines()
-                                .map(|line| {
-                                        if line == "status: OPEN" {
-                                                "status: CLOSED".to_string()
-                                        } else {
-                                                line.to_string()
-                                        }
-                                })
-                                .collect();
+                let path = store.root.join(folder_name).join("TASK.md");
+                let content =
+                        fs::read_to_string(&path).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let depends = graph.get(huid).cloned().unwrap_or_default();
+                let content =
+                        set_front_matter(&content, "depends", &format!("[{}]", depends.join(", ")));
+                atomic::write(&path, content).map_err(|e| lexopt::Error::Custom(e.into()))?;
 
-                        // Join with newlines and append a final newline to match typical file behavior
-                        let new_content = new_lines.join("\n") + "\n";
+                for (dependency_path, dependency) in added {
+                        println!(
+                                "Task [{}] '{}' now depends on [{}] '{}'.",
+                                short_id(folder_name),
+                                task.title,
+                                short_id(dependency_path),
+                                dependency.title
+                        );
+                }
+                Ok(())
+        }
 
-                        std::fs::write(&path, new_content)
-                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+        pub fn graph(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut args = ListArgs::default();
+
+                loop {
+                        if let Some(tag) = take_tag_removal(parser, "hcg", "tq") {
+                                args.without_tags.push(tag);
+                                continue;
+                        }
+
+                        let Some(arg) = parser.next()? else {
+                                break;
+                        };
+
+                        match arg {
+                                #[rustfmt::skip]
+                                Short('h') | Long("help") => {
+                                        println!("tafsk-graph");
+                                        println!();
+                                        println!("USAGE:");
+                                        println!("    tafsk graph [FLAGS] [+TAG]... [-TAG]...");
+                                        println!();
+                                        println!("FLAGS:");
+                                        println!("    -h, --help             Prints help information");
+                                        println!("    -c, --closed           Include closed tasks");
+                                        println!("    -g, --global           Include tasks from the default store");
+                                        println!("    -t, --tag <TAG,...>    Include tasks with any of the listed tags");
+                                        println!("    -q, --query <EXPR>     Include tasks matching EXPR");
+                                        println!("        --ready            Include open tasks whose dependencies are closed");
+                                        println!("        --blocked          Include tasks waiting for an open dependency");
+                                        println!("        --overdue          Include tasks whose due date has passed");
+                                        println!("        --due-before <WHEN>");
+                                        println!("                           Include tasks due before WHEN");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    +TAG                   Include only tasks with this tag");
+                                        println!("    -TAG                   Leave out tasks with this tag");
+                                        return Ok(());
+                                },
+                                Short('t') => handle_selector_arg(parser, &mut args, "tag")?,
+                                Short('q') => handle_selector_arg(parser, &mut args, "query")?,
+                                Long(flag) if SELECTOR_FLAGS.contains(&flag) => {
+                                        let flag = flag.to_string();
+                                        handle_selector_arg(parser, &mut args, &flag)?
+                                },
+                                _ => handle_list_arg(&mut args, arg)?,
+                        }
+                }
 
-                        println!("Completed task [{}] '{}'.", id, task.title);
+                // A query about the status decides on its own which tasks are shown
+                if args.query.as_ref().is_some_and(Expr::mentions_status) {
+                        args.show_closed = true;
                 }
+
+                let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let tasks = store
+                        .list_tasks()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let timezone = store
+                        .timezone()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let workflow = store
+                        .workflow()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                let due_limit = args.due_limit(datetime::now(), &timezone)?;
+                let is_closed_by_huid = closed_by_huid(&tasks, &workflow);
+                let shown =
+                        args.select(&tasks, &workflow, &is_closed_by_huid, due_limit, &timezone);
+
+                println!("{}", graph::render(&shown, &workflow));
                 Ok(())
         }
 
@@ -234,24 +1168,22 @@ impl Task {
 
                 loop {
                         // Negative offsets shouldn't be recognized as flags
-                        if let Some(mut raw) = parser.try_raw_args() {
-                                if let Some(arg) = raw.peek() {
+                        if let Some(mut raw) = parser.try_raw_args()
+                                && raw.peek().is_some_and(|arg| {
                                         let s = arg.to_string_lossy();
-                                        if s.starts_with('-')
+                                        s.starts_with('-')
                                                 && s.chars()
                                                         .nth(1)
-                                                        .map_or(false, |c| c.is_ascii_digit())
-                                        {
-                                                let val = raw.next().unwrap();
-                                                timezone = Some(val.to_string_lossy().into_owned());
-                                                continue;
-                                        }
-                                }
+                                                        .is_some_and(|c| c.is_ascii_digit())
+                                })
+                                && let Some(val) = raw.next()
+                        {
+                                timezone = Some(val.to_string_lossy().into_owned());
+                                continue;
                         }
 
-                        let match_arg = match parser.next()? {
-                                Some(arg) => arg,
-                                None => break,
+                        let Some(match_arg) = parser.next()? else {
+                                break;
                         };
 
                         match match_arg {
@@ -264,7 +1196,7 @@ impl Task {
                                         println!("    tafsk init [TIMEZONE]");
                                         println!();
                                         println!("ARGS:");
-                                        println!("    <TIMEZONE>       Timezone offset (e.g., +09:30, -05:00)");
+                                        println!("    <TIMEZONE>       Timezone offset (e.g., +09:30, -05:00) or name (e.g., America/Sao_Paulo)");
                                         println!("                     Defaults to +00:00 (UTC)");
                                         println!();
                                         println!("FLAGS:");
@@ -281,7 +1213,10 @@ impl Task {
                         .map_err(|e| lexopt::Error::Custom(e.into()))?
                         .join("tasks");
 
+                // Checked before the lock creates the store
+                TimeZone::load(&timezone).map_err(|e| lexopt::Error::Custom(e.into()))?;
                 let store = Store::from_path(path);
+                let _lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
                 store.update_config(&timezone)
                         .map_err(|e| lexopt::Error::Custom(e.into()))?;
 
@@ -293,86 +1228,203 @@ impl Task {
                 Ok(())
         }
 
-        pub fn list(mut args: ListArgs, parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
-                while let Some(arg) = parser.next()? {
+        pub fn list(parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
+                let mut args = ListArgs::default();
+
+                loop {
+                        if let Some(tag) = take_tag_removal(parser, "hcg", "tqf") {
+                                args.without_tags.push(tag);
+                                continue;
+                        }
+
+                        let Some(arg) = parser.next()? else {
+                                break;
+                        };
+
                         match arg {
                                 #[rustfmt::skip]
                                 Short('h') | Long("help") => {
                                         println!("tafsk-list");
                                         println!();
                                         println!("USAGE:");
-                                        println!("    tafsk list [FLAGS]");
+                                        println!("    tafsk list [FLAGS] [+TAG]... [-TAG]...");
                                         println!();
                                         println!("FLAGS:");
-                                        println!("    -h, --help       Prints help information");
-                                        println!("    -c, --closed     Show closed tasks");
-                                        println!("    -g, --global     Show tasks from the default store");
+                                        println!("    -h, --help             Prints help information");
+                                        println!("    -c, --closed           Show closed tasks");
+                                        println!("    -g, --global           Show tasks from the default store");
+                                        println!("    -t, --tag <TAG,...>    Show tasks with any of the listed tags");
+                                        println!("    -q, --query <EXPR>     Show tasks matching EXPR");
+                                        println!("    -f, --format <FORMAT>  Output as json, jsonl, a named template or a template");
+                                        println!("        --line <KEY>       Point at the title (default) or a front matter key");
+                                        println!("        --sort <KEY,...>   Sort by priority, created, closed, due, tag or title,");
+                                        println!("                           descending with a leading '-' (default: -priority)");
+                                        println!("        --age              Show how long ago tasks were created");
+                                        println!("        --tree             Show subtasks indented under their parent");
+                                        println!("        --ready            Show open tasks whose dependencies are closed");
+                                        println!("        --blocked          Show tasks waiting for an open dependency");
+                                        println!("        --overdue          Show tasks whose due date has passed");
+                                        println!("        --due-before <WHEN>");
+                                        println!("                           Show tasks due before WHEN");
+                                        println!();
+                                        println!("ARGS:");
+                                        println!("    +TAG                   Show only tasks with this tag");
+                                        println!("    -TAG                   Hide tasks with this tag");
                                         return Ok(());
                                 },
+                                Short('f') | Long("format") => {
+                                        args.format = Some(parser.value()?.string()?)
+                                },
+                                Long("line") => args.line = Some(parser.value()?.string()?),
+                                Long("sort") => args.sort = Some(parser.value()?.string()?),
+                                Long("age") => args.show_age = true,
+                                Long("tree") => args.tree = true,
+                                Short('t') => handle_selector_arg(parser, &mut args, "tag")?,
+                                Short('q') => handle_selector_arg(parser, &mut args, "query")?,
+                                Long(flag) if SELECTOR_FLAGS.contains(&flag) => {
+                                        let flag = flag.to_string();
+                                        handle_selector_arg(parser, &mut args, &flag)?
+                                },
                                 _ => handle_list_arg(&mut args, arg)?,
                         }
                 }
 
+                // A query about the status decides on its own which tasks are shown
+                if args.query.as_ref().is_some_and(Expr::mentions_status) {
+                        args.show_closed = true;
+                }
+
                 let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
                 let tasks = store
                         .list_tasks()
                         .map_err(|e| lexopt::Error::Custom(e.into()))?;
 
-                if tasks.is_empty() {
+                // A key that no task has is more likely a typo than a choice
+                if let Some(key) = &args.line
+                        && key != "title"
+                        && !FRONT_MATTER_KEYS.contains(&key.as_str())
+                        && !tasks.iter().any(|(_, task)| task.line_of(key).is_some())
+                {
+                        return Err(lexopt::Error::Custom(
+                                format!(
+                                        "Unknown key '{}' for --line. Expected title or a front matter key, such as status or due",
+                                        key
+                                )
+                                .into(),
+                        ));
+                }
+
+                let format_spec = match args.format.take() {
+                        Some(spec) => Some(spec),
+                        None => store
+                                .setting("format")
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?,
+                };
+                let format = Format::resolve(format_spec.as_deref(), &store)?;
+                if tasks.is_empty() && matches!(format, Format::Template(_)) {
                         println!("No tasks found.");
                         return Ok(());
                 }
 
-                let mut indexed_tasks: Vec<_> = tasks
-                        .into_iter()
-                        .enumerate()
-                        // indexes are lua-pilled
-                        .map(|(i, (folder, task))| (i + 1, folder, task))
-                        .collect();
-                indexed_tasks.sort_by(|a, b| b.2.priority.cmp(&a.2.priority));
+                let timezone = store
+                        .timezone()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let workflow = store
+                        .workflow()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let id_format = store
+                        .id_format()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
 
-                for (display_idx, folder_name, task) in indexed_tasks {
-                        if task.status == Status::Closed && !args.show_closed {
-                                continue;
-                        }
+                let sort_spec = match args.sort.take() {
+                        Some(spec) => spec,
+                        None => store
+                                .setting("sort")
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?
+                                .unwrap_or_else(|| DEFAULT_SORT.to_string()),
+                };
+                let keys = sort::parse(&sort_spec).map_err(|e| lexopt::Error::Custom(e.into()))?;
 
-                        let tags = task.tags.join(",");
-                        let path = store.root.join(&folder_name).join("TASK.md");
+                let now = datetime::now();
+                let due_limit = args.due_limit(now, &timezone)?;
 
-                        let mut parts = vec![];
+                let mut tasks = tasks;
+                tasks.sort_by(|a, b| sort::compare(&keys, a, b, &timezone));
 
-                        parts.push(format!("[PRIORITY: {:>3}]", task.priority));
-                        if task.status != Status::Open {
-                                parts.push("[STATUS: CLOSED]".to_string())
-                        }
-                        if !tags.is_empty() {
-                                parts.push(format!("[TAGS: {}]", tags))
+                let is_closed_by_huid = closed_by_huid(&tasks, &workflow);
+                let shown =
+                        args.select(&tasks, &workflow, &is_closed_by_huid, due_limit, &timezone);
+                let shown = if args.tree {
+                        tree_order(&shown)
+                } else {
+                        shown.into_iter().map(|task| (task, 0)).collect()
+                };
+
+                let mut json_objects = Vec::new();
+                for ((folder_name, task), depth) in shown {
+                        let created_at = created_at(folder_name, task, &timezone);
+
+                        let entry = Entry {
+                                huid: huid_of(folder_name),
+                                parent: parent_of(folder_name).map(huid_of),
+                                path: store.root.join(folder_name).join("TASK.md"),
+                                task,
+                                created: created_at.map(|created| {
+                                        datetime::format_rfc3339(created, &timezone)
+                                }),
+                                age: created_at.map(|created| datetime::format_age(now - created)),
+                                closed: task
+                                        .closed
+                                        .map(|closed| datetime::format_rfc3339(closed, &timezone)),
+                                due: task.due.map(|due| datetime::format_rfc3339(due, &timezone)),
+                                now,
+                                show_age: args.show_age,
+                                workflow: &workflow,
+                                id_format,
+                                depth,
+                                progress: progress(&tasks, folder_name, &workflow),
+                                blocked_by: open_dependencies(task, &is_closed_by_huid),
+                                line: args
+                                        .line
+                                        .as_deref()
+                                        .and_then(|key| task.line_of(key))
+                                        .unwrap_or(task.title_line),
+                        };
+
+                        match &format {
+                                Format::Template(template) => {
+                                        println!("{}", template.render(&entry))
+                                },
+                                Format::Jsonl => println!("{}", entry.to_json()),
+                                Format::Json => json_objects.push(entry.to_json()),
                         }
+                }
 
-                        println!(
-                                "{}:7:{}: {} {}",
-                                path.display(),
-                                display_idx,
-                                parts.join(" "),
-                                task.title
-                        );
+                if matches!(format, Format::Json) {
+                        println!("[{}]", json_objects.join(","));
                 }
 
                 Ok(())
         }
 
-        fn render(&self) -> String {
-                let lines = vec![
+        /// Renders a new task file. Times are written in `timezone`.
+        fn render(&self, timezone: &TimeZone) -> String {
+                let mut lines = vec![
                         format!("---"),
                         format!("status: {}", self.status),
                         format!("priority: {}", self.priority),
                         format!("tags: [{}]", self.tags.join(", ")),
+                ];
+                if let Some(due) = self.due {
+                        lines.push(format!("due: {}", datetime::format_rfc3339(due, timezone)));
+                }
+                lines.extend(vec![
                         format!("---"),
                         format!(""),
                         format!("# {}", self.title),
                         format!(""),
                         format!("\n"), // Last item doesn't get newlines
-                ];
+                ]);
 
                 lines.join("\n")
         }
@@ -384,12 +1436,20 @@ impl FromStr for Task {
         fn from_str(s: &str) -> Result<Self, Self::Err> {
                 let mut status: Option<Status> = None;
                 let mut priority: Option<u8> = None;
+                let mut created: Option<i64> = None;
+                let mut closed: Option<i64> = None;
+                let mut due: Option<i64> = None;
                 let mut tags: Option<Vec<String>> = None;
+                let mut depends: Vec<String> = Vec::new();
                 let mut title: Option<String> = None;
 
-                let mut lines = s.lines();
+                let mut title_line = 0;
+                let mut key_lines = Vec::new();
 
-                if lines.next() != Some("---") {
+                // Line numbers are 1-based, like the ones editors jump to
+                let mut lines = s.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+                if lines.next().map(|(_, l)| l) != Some("---") {
                         return Err(lexopt::Error::Custom(
                                 "Task file missing front matter delimiter '---'"
                                         .to_string()
@@ -397,10 +1457,11 @@ impl FromStr for Task {
                         ));
                 }
 
-                for line in lines.by_ref().take_while(|l| *l != "---") {
+                for (n, line) in lines.by_ref().take_while(|(_, l)| *l != "---") {
                         let Some((k, v)) = line.split_once(": ") else {
                                 continue;
                         };
+                        key_lines.push((k.trim().to_string(), n));
 
                         match k.trim() {
                                 "status" => status = Some(Status::from_str(v.trim())?),
@@ -411,26 +1472,42 @@ impl FromStr for Task {
                                                 )
                                         })?);
                                 },
-                                "tags" => {
-                                        let cleaned_value =
-                                                v.trim().strip_prefix('[')
-                                                        .and_then(|t| t.strip_suffix(']'))
-                                                        .unwrap_or(v.trim());
-
-                                        if cleaned_value.is_empty() {
-                                                tags = Some(Vec::new());
-                                        } else {
-                                                tags = Some(cleaned_value
-                                                        .split(',')
-                                                        .map(|t| t.trim().to_string())
-                                                        .collect());
-                                        }
+                                "created" => {
+                                        let invalid = || {
+                                                lexopt::Error::Custom(
+                                                        format!("Invalid created time: '{v}'")
+                                                                .into(),
+                                                )
+                                        };
+                                        created = Some(DateTime::parse_rfc3339(v.trim())
+                                                .ok_or_else(invalid)?);
+                                },
+                                "closed" => {
+                                        let invalid = || {
+                                                lexopt::Error::Custom(
+                                                        format!("Invalid closed time: '{v}'")
+                                                                .into(),
+                                                )
+                                        };
+                                        closed = Some(DateTime::parse_rfc3339(v.trim())
+                                                .ok_or_else(invalid)?);
+                                },
+                                "due" => {
+                                        let invalid = || {
+                                                lexopt::Error::Custom(
+                                                        format!("Invalid due time: '{v}'").into(),
+                                                )
+                                        };
+                                        due = Some(DateTime::parse_rfc3339(v.trim())
+                                                .ok_or_else(invalid)?);
                                 },
+                                "tags" => tags = Some(parse_list(v)),
+                                "depends" => depends = parse_list(v),
                                 _ => { /* Ignore unknown front matter keys */ },
                         }
                 }
 
-                for line in lines {
+                for (n, line) in lines.by_ref() {
                         // Skip empty lines between front matter and title
                         if line.trim().is_empty() {
                                 continue;
@@ -438,6 +1515,7 @@ impl FromStr for Task {
 
                         if let Some(t) = line.strip_prefix("# ") {
                                 title = Some(t.to_string());
+                                title_line = n;
                                 break;
                         } else {
                                 #[rustfmt::skip]
@@ -453,12 +1531,535 @@ impl FromStr for Task {
                         priority: priority.ok_or_else(|| { lexopt::Error::Custom("Task file missing priority".to_string().into()) })?,
                         status: status.ok_or_else(|| { lexopt::Error::Custom("Task file missing status".to_string().into()) })?,
                         tags: tags.unwrap_or_default(),
+                        created,
+                        closed,
+                        due,
+                        depends,
+                        body: lines.map(|(_, l)| l).collect::<Vec<_>>().join("\n").trim_matches('\n').to_string(),
+                        title_line,
+                        key_lines,
                 };
 
                 Ok(task)
         }
 }
 
+/// Parses a front matter list such as `[work, home]`.
+fn parse_list(value: &str) -> Vec<String> {
+        let cleaned_value = value
+                .trim()
+                .strip_prefix('[')
+                .and_then(|t| t.strip_suffix(']'))
+                .unwrap_or(value.trim());
+
+        if cleaned_value.is_empty() {
+                Vec::new()
+        } else {
+                cleaned_value
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .collect()
+        }
+}
+
+/// Resolves `ids` and appends the tasks matching the query of `args`.
+///
+/// Every ID is resolved before anything is returned, so that a typo doesn't
+/// leave a batch of changes half-applied.
+fn select_tasks(
+        tasks: &[(String, Task)],
+        ids: &[String],
+        args: &ListArgs,
+        timezone: &TimeZone,
+) -> Result<Vec<usize>, lexopt::Error> {
+        let mut selected = Vec::new();
+        for id in ids {
+                let idx = find_task(tasks, id).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                if !selected.contains(&idx) {
+                        selected.push(idx);
+                }
+        }
+
+        if args.query.is_some() {
+                for (idx, (folder_name, task)) in tasks.iter().enumerate() {
+                        if args.matches(folder_name, task, timezone) && !selected.contains(&idx) {
+                                selected.push(idx);
+                        }
+                }
+        }
+
+        Ok(selected)
+}
+
+/// Returns the tasks leading from `from` to `to` by following dependencies,
+/// both ends included, if `from` depends on `to` at all.
+fn dependency_chain<'a>(
+        graph: &HashMap<&'a str, Vec<&'a str>>,
+        from: &'a str,
+        to: &str,
+) -> Option<Vec<&'a str>> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![vec![from]];
+        while let Some(chain) = stack.pop() {
+                let Some(&last) = chain.last() else {
+                        continue;
+                };
+                if last == to {
+                        return Some(chain);
+                }
+                if !seen.insert(last) {
+                        continue;
+                }
+                for &next in graph.get(last).into_iter().flatten() {
+                        let mut longer = chain.clone();
+                        longer.push(next);
+                        stack.push(longer);
+                }
+        }
+        None
+}
+
+/// Returns the HUIDs of the dependencies of `task` that are still open.
+/// Dependencies that aren't in the store don't block anything.
+fn open_dependencies<'a>(task: &'a Task, is_closed_by_huid: &HashMap<&str, bool>) -> Vec<&'a str> {
+        task.depends
+                .iter()
+                .map(String::as_str)
+                .filter(|huid| is_closed_by_huid.get(huid) == Some(&false))
+                .collect()
+}
+
+/// Orders `tasks` so that subtasks follow their parent, one level deeper.
+/// Tasks whose parent isn't among `tasks` follow their closest ancestor that
+/// is, if any, and siblings keep their order.
+fn tree_order<'a>(tasks: &[&'a (String, Task)]) -> Vec<(&'a (String, Task), usize)> {
+        // The closest ancestor of each task that is listed
+        let parents: Vec<Option<usize>> = tasks
+                .iter()
+                .map(|(path, _)| {
+                        let mut ancestor = parent_of(path);
+                        while let Some(ancestor_path) = ancestor {
+                                if let Some(idx) =
+                                        tasks.iter().position(|(p, _)| p == ancestor_path)
+                                {
+                                        return Some(idx);
+                                }
+                                ancestor = parent_of(ancestor_path);
+                        }
+                        None
+                })
+                .collect();
+
+        let mut ordered = Vec::with_capacity(tasks.len());
+        let mut stack: Vec<(usize, usize)> = (0..tasks.len())
+                .rev()
+                .filter(|&idx| parents[idx].is_none())
+                .map(|idx| (idx, 0))
+                .collect();
+        while let Some((idx, depth)) = stack.pop() {
+                ordered.push((tasks[idx], depth));
+                stack.extend((0..tasks.len())
+                        .rev()
+                        .filter(|&child| parents[child] == Some(idx))
+                        .map(|child| (child, depth + 1)));
+        }
+        ordered
+}
+
+/// Counts the subtasks of the task at `task_path` that are closed, and all of
+/// them. Only direct subtasks are counted, and tasks without any have no
+/// count.
+fn progress(
+        tasks: &[(String, Task)],
+        task_path: &str,
+        workflow: &Workflow,
+) -> Option<(usize, usize)> {
+        let children: Vec<&Task> = tasks
+                .iter()
+                .filter(|(path, _)| parent_of(path) == Some(task_path))
+                .map(|(_, task)| task)
+                .collect();
+        if children.is_empty() {
+                return None;
+        }
+
+        let closed = children
+                .iter()
+                .filter(|task| workflow.is_closed(task.status.as_str()))
+                .count();
+        Some((closed, children.len()))
+}
+
+/// Which configuration `tafsk config` reads or writes.
+#[derive(Clone, Copy)]
+enum Scope {
+        /// The settings in effect, from wherever they come from.
+        Effective,
+        Store,
+        User,
+}
+
+/// Returns the `(key, value, source)` of the settings of `scope`.
+///
+/// The settings in effect are, from first to last choice, those of the
+/// store, $TAFSK_STORE_DIR, those of the user, $EDITOR and the built-in
+/// defaults. Known settings come first, in the order of
+/// `config::KEYS`.
+fn settings(
+        store: Option<&Store>,
+        scope: Scope,
+) -> io::Result<Vec<(String, String, &'static str)>> {
+        let mut settings: Vec<(String, String, &'static str)> = Vec::new();
+        let mut add = |key: &str, value: String, source: &'static str| {
+                if !settings.iter().any(|(k, ..)| k == key) {
+                        settings.push((key.to_string(), value, source));
+                }
+        };
+
+        if let Some(store) = store
+                && !matches!(scope, Scope::User)
+        {
+                for (key, value) in store.config()?.settings() {
+                        add(key, value, "store");
+                }
+        }
+        if matches!(scope, Scope::Effective)
+                && let Ok(dir) = env::var("TAFSK_STORE_DIR")
+        {
+                add("store", dir, "environment");
+        }
+        if !matches!(scope, Scope::Store) {
+                for (key, value) in Config::user()?.settings() {
+                        add(key, value, "user");
+                }
+        }
+        if matches!(scope, Scope::Effective) {
+                if let Ok(editor) = env::var("EDITOR") {
+                        add("editor", editor, "environment");
+                }
+
+                add("timezone", "+00:00".to_string(), "default");
+                add("default_priority", DEFAULT_PRIORITY.to_string(), "default");
+                add("editor", "vi".to_string(), "default");
+                add("id_format", "hash".to_string(), "default");
+                add("format", "quickfix".to_string(), "default");
+                add("sort", DEFAULT_SORT.to_string(), "default");
+                add("workflow", Workflow::default().to_string(), "default");
+        }
+
+        settings.sort_by_key(|(key, ..)| {
+                config::KEYS
+                        .iter()
+                        .position(|k| k == key)
+                        .unwrap_or(config::KEYS.len())
+        });
+        Ok(settings)
+}
+
+/// What `set_status` does to the status of tasks.
+enum Change {
+        /// `done`: to the first closed state.
+        Close,
+        /// `reopen`: back to the initial state.
+        Reopen,
+        /// `status`: to any state the workflow allows, or any state at all
+        /// when forced.
+        Move { state: String, force: bool },
+}
+
+fn set_status(args: &ListArgs, ids: &[String], change: &Change) -> Result<(), lexopt::Error> {
+        let store = Store::new(args.show_global).map_err(|e| lexopt::Error::Custom(e.into()))?;
+        let _lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
+        let tasks = store
+                .list_tasks()
+                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+        let timezone = store
+                .timezone()
+                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+        let workflow = store
+                .workflow()
+                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+        let now = datetime::now();
+
+        let target = match change {
+                Change::Close => workflow.closed(),
+                Change::Reopen => workflow.initial(),
+                Change::Move { state, .. } => workflow.find(state).ok_or_else(|| {
+                        lexopt::Error::Custom(
+                                format!(
+                                        "Unknown state '{}'. Expected one of: {}",
+                                        state,
+                                        workflow.states().collect::<Vec<_>>().join(", ")
+                                )
+                                .into(),
+                        )
+                })?,
+        };
+
+        // Tasks that are already where they would be moved
+        let is_done = |status: &Status| match change {
+                Change::Close => workflow.is_closed(status.as_str()),
+                Change::Reopen => !workflow.is_closed(status.as_str()),
+                Change::Move { .. } => status.as_str().eq_ignore_ascii_case(target),
+        };
+
+        let mut selected = select_tasks(&tasks, ids, &ListArgs::default(), &timezone)?;
+        if args.query.is_some() {
+                // Tasks matched by a query are skipped when there is nothing to change
+                let matched = select_tasks(&tasks, &[], args, &timezone)?;
+                for idx in matched {
+                        if !is_done(&tasks[idx].1.status) && !selected.contains(&idx) {
+                                selected.push(idx);
+                        }
+                }
+        }
+
+        // Nothing is written until every task is known to be movable
+        for &idx in &selected {
+                let (folder_name, task) = &tasks[idx];
+                let status = task.status.as_str();
+
+                let problem = if is_done(&task.status) {
+                        if matches!(change, Change::Reopen) && !workflow.is_initial(status) {
+                                Some(format!("is not closed, it is {}", status))
+                        } else {
+                                Some(format!("is already {}", status))
+                        }
+                } else if let Change::Move { force: false, .. } = change
+                        && workflow.find(status).is_some()
+                        && !workflow.can_move(status, target)
+                {
+                        Some(format!(
+                                "can't move from {} to {}. Expected one of: {}",
+                                status,
+                                target,
+                                workflow.next_states(status).join(", ")
+                        ))
+                } else if let Change::Move { force: false, .. } = change
+                        && workflow.find(status).is_none()
+                        && !workflow.is_initial(target)
+                {
+                        // Where it stands in the workflow is anyone's guess
+                        Some(format!(
+                                "is in {}, which is not part of the workflow. Move it to {} or use --force",
+                                status,
+                                workflow.initial()
+                        ))
+                } else {
+                        None
+                };
+
+                if let Some(problem) = problem {
+                        return Err(lexopt::Error::Custom(
+                                format!(
+                                        "Task [{}] '{}' {}.",
+                                        short_id(folder_name),
+                                        task.title,
+                                        problem
+                                )
+                                .into(),
+                        ));
+                }
+        }
+
+        for idx in selected {
+                let (folder_name, task) = &tasks[idx];
+
+                let path = store.root.join(folder_name).join("TASK.md");
+                let content =
+                        fs::read_to_string(&path).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let new_content = set_front_matter(&content, "status", target);
+                // Closing records when it happened, reopening forgets it
+                let was_closed = workflow.is_closed(task.status.as_str());
+                let new_content = match (was_closed, workflow.is_closed(target)) {
+                        (false, true) => set_front_matter(
+                                &new_content,
+                                "closed",
+                                &datetime::format_rfc3339(now, &timezone),
+                        ),
+                        (_, false) => remove_front_matter(&new_content, "closed"),
+                        (true, true) => new_content,
+                };
+
+                atomic::write(&path, new_content).map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                match change {
+                        Change::Close => println!(
+                                "Completed task [{}] '{}'.",
+                                short_id(folder_name),
+                                task.title
+                        ),
+                        Change::Reopen => println!(
+                                "Reopened task [{}] '{}'.",
+                                short_id(folder_name),
+                                task.title
+                        ),
+                        Change::Move { .. } => println!(
+                                "Moved task [{}] '{}' from {} to {}.",
+                                short_id(folder_name),
+                                task.title,
+                                task.status,
+                                target
+                        ),
+                }
+        }
+        Ok(())
+}
+
+/// Takes the next argument if it is a `-TAG` selector rather than a flag.
+///
+/// `switches` lists the short flags of the subcommand that take no value and
+/// `valued` those that take one. Like getopt, anything after a valued flag is
+/// its value, so `-cg`, `-p50` and `-fjson` are still parsed as flags, while
+/// `-blocked` is a tag. Tags that read as flags can be given after `--`.
+fn take_tag_removal(parser: &mut lexopt::Parser, switches: &str, valued: &str) -> Option<String> {
+        let mut raw = parser.try_raw_args()?;
+        let arg = raw.peek()?.to_str()?;
+        let tag = arg
+                .strip_prefix('-')
+                .filter(|t| !t.is_empty() && !t.starts_with('-'))?;
+
+        let mut chars = tag.chars();
+        let is_flag_cluster = loop {
+                match chars.next() {
+                        None => break true,
+                        Some(c) if switches.contains(c) => continue,
+                        Some(c) if valued.contains(c) => break true,
+                        Some(_) => break false,
+                }
+        };
+
+        if is_flag_cluster {
+                return None;
+        }
+
+        let tag = tag.to_string();
+        raw.next();
+        Some(tag)
+}
+
+/// Returns the index of the closing front matter delimiter among `lines`.
+fn front_matter_end(lines: &[&str]) -> Option<usize> {
+        if lines.first().map(|l| l.trim_end()) != Some("---") {
+                return None;
+        }
+        lines.iter()
+                .skip(1)
+                .position(|l| l.trim_end() == "---")
+                .map(|i| i + 1)
+}
+
+/// Sets front matter `key` to `value`, adding the key if it is missing.
+///
+/// Every other line, including unknown keys and the body, is kept verbatim.
+pub fn set_front_matter(content: &str, key: &str, value: &str) -> String {
+        let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let Some(end) = front_matter_end(&lines) else {
+                return content.to_string();
+        };
+
+        let new_line = format!("{}: {}\n", key, value);
+        let existing = lines[1..end]
+                .iter()
+                .position(|l| l.split_once(':').is_some_and(|(k, _)| k.trim() == key));
+
+        match existing {
+                Some(i) => lines[i + 1] = &new_line,
+                None => lines.insert(end, &new_line),
+        }
+
+        lines.concat()
+}
+
+/// Removes front matter `key`, if present.
+fn remove_front_matter(content: &str, key: &str) -> String {
+        let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let Some(end) = front_matter_end(&lines) else {
+                return content.to_string();
+        };
+
+        if let Some(i) = lines[1..end]
+                .iter()
+                .position(|l| l.split_once(':').is_some_and(|(k, _)| k.trim() == key))
+        {
+                lines.remove(i + 1);
+        }
+
+        lines.concat()
+}
+
+/// Replaces the `# ` title line that follows the front matter.
+fn set_title(content: &str, title: &str) -> String {
+        let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let start = front_matter_end(&lines).map_or(0, |end| end + 1);
+
+        let new_line = format!("# {}\n", title);
+        if let Some(i) = lines[start..].iter().position(|l| l.starts_with("# ")) {
+                lines[start + i] = &new_line;
+        }
+
+        lines.concat()
+}
+
+/// Opens `path` in the editor of `store` until it holds a task that parses.
+///
+/// When the edited file is invalid, the user may re-open the editor or
+/// restore `original`, which is also what happens if stdin is closed.
+fn edit_until_valid(store: &Store, path: &Path, original: &str) -> Result<(), lexopt::Error> {
+        let editor = store
+                .editor()
+                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+        // The editor may come with arguments, such as `code --wait`
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or("vi");
+        let editor_args: Vec<&str> = words.collect();
+
+        loop {
+                let status = Command::new(program)
+                        .args(&editor_args)
+                        .arg(path)
+                        .status()
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                if !status.success() {
+                        return Err(lexopt::Error::Custom(
+                                format!("Editor exited with error: {}", status).into(),
+                        ));
+                }
+
+                // Held while the result is checked, but not while the editor
+                // or the question below wait for the user
+                let lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let content =
+                        fs::read_to_string(path).map_err(|e| lexopt::Error::Custom(e.into()))?;
+                let Err(parse_err) = Task::from_str(&content) else {
+                        return Ok(());
+                };
+                drop(lock);
+
+                eprintln!(
+                        "[task]: {} is not a valid task: {}",
+                        path.display(),
+                        parse_err
+                );
+                eprint!("[task]: (e)dit again or (r)estore previous content? [E/r] ");
+
+                let mut answer = String::new();
+                let read = io::stdin()
+                        .read_line(&mut answer)
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?;
+
+                if read == 0 || answer.trim().eq_ignore_ascii_case("r") {
+                        let _lock = store.lock().map_err(|e| lexopt::Error::Custom(e.into()))?;
+                        atomic::write(path, original)
+                                .map_err(|e| lexopt::Error::Custom(e.into()))?;
+                        eprintln!("[task]: Restored previous content of {}", path.display());
+                        return Ok(());
+                }
+        }
+}
+
 pub fn print_global_help() {
         println!("tafsk {}", env!("CARGO_PKG_VERSION"));
         println!();
@@ -471,7 +2072,15 @@ pub fn print_global_help() {
         println!();
         println!("SUBCOMMANDS:");
         println!("    add              Create a new task");
+        println!("    config           Show or change settings");
+        println!("    depend           Make a task wait for others");
         println!("    done             Mark a task as closed");
+        println!("    edit             Open a task in the editor");
+        println!("    graph            Print tasks and their links as a Graphviz graph");
         println!("    init             Initialize store with timezone");
         println!("    list             List all tasks");
+        println!("    modify           Change priority, tags or title of tasks");
+        println!("    mv               Move a task under another one or to another store");
+        println!("    reopen           Mark a closed task as open");
+        println!("    status           Move tasks to another state of the workflow");
 }
@@ -0,0 +1,261 @@
+use std::path::{
+        Path,
+        PathBuf,
+};
+use std::{
+        env,
+        fs,
+        io,
+};
+
+use crate::format::Template;
+use crate::tz::TimeZone;
+use crate::workflow::Workflow;
+use crate::{
+        atomic,
+        sort,
+};
+
+/// Settings that `tafsk config set` accepts, besides `format.NAME`.
+pub const KEYS: &[&str] = &[
+        "timezone",
+        "default_priority",
+        "editor",
+        "id_format",
+        "format",
+        "sort",
+        "workflow",
+        "store",
+];
+
+/// Comments written at the top of a new configuration.
+const HEADER: &[&str] = &[
+        "# Configuration for tafsk store",
+        "#",
+        "# Settings are 'key = value' lines, with values optionally in double quotes:",
+        "#   timezone          +09:30, -05:00 or a zone name such as America/Sao_Paulo",
+        "#   default_priority  Priority of new tasks (0-255, default: 10)",
+        "#   editor            Command used by 'edit' and 'add -e' instead of $EDITOR",
+        "#   id_format         IDs shown by 'list': hash (default) or huid",
+        "#",
+        "# Settings missing here are read from the user configuration, see 'tafsk config'.",
+];
+
+/// How `list` shows the ID of a task.
+#[derive(Clone, Copy, Default)]
+pub enum IdFormat {
+        /// The hash component of the HUID, such as `f3fe84e5`.
+        #[default]
+        Hash,
+        /// The full HUID, such as `20251219-010809.f3fe84e5`.
+        Huid,
+}
+
+impl IdFormat {
+        pub fn parse(s: &str) -> Result<Self, String> {
+                match s {
+                        "hash" => Ok(Self::Hash),
+                        "huid" => Ok(Self::Huid),
+                        _ => Err(format!("'{}'. Expected hash or huid", s)),
+                }
+        }
+}
+
+/// The `.config` file of a store: `key = value` settings and `#` comments.
+///
+/// Lines are kept as written, so that rewriting a setting leaves comments and
+/// the other settings alone.
+#[derive(Default)]
+pub struct Config {
+        lines:    Vec<String>,
+        /// Whether the file was in the legacy form, where the first line that
+        /// is not a comment holds the bare timezone.
+        migrated: bool,
+}
+
+impl Config {
+        /// The configuration of the user, which stores fall back to for the
+        /// settings they don't have. Users without one have an empty
+        /// configuration.
+        pub fn user() -> io::Result<Self> {
+                match user_path() {
+                        Some(path) => Self::read(&path),
+                        None => Ok(Self::default()),
+                }
+        }
+
+        /// A configuration for a new store, in the given timezone.
+        pub fn new(timezone: &str) -> Self {
+                let mut config = Self {
+                        lines:    HEADER.iter().map(|l| l.to_string()).collect(),
+                        migrated: false,
+                };
+                config.set("timezone", timezone);
+                config
+        }
+
+        pub fn parse(content: &str) -> Self {
+                let mut config = Self::default();
+
+                for line in content.lines() {
+                        let trimmed = line.trim();
+                        let is_setting = trimmed.contains('=');
+                        if !config.migrated
+                                && !is_setting
+                                && !trimmed.is_empty()
+                                && !trimmed.starts_with('#')
+                                && config.get("timezone").is_none()
+                        {
+                                config.lines.push(setting_line("timezone", trimmed));
+                                config.migrated = true;
+                        } else {
+                                config.lines.push(line.to_string());
+                        }
+                }
+
+                config
+        }
+
+        /// Reads the configuration at `path`. A missing file is an empty
+        /// configuration.
+        pub fn read(path: &Path) -> io::Result<Self> {
+                match fs::read_to_string(path) {
+                        Ok(content) => Ok(Self::parse(&content)),
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+                        Err(e) => Err(e),
+                }
+        }
+
+        pub fn write(&self, path: &Path) -> io::Result<()> {
+                let mut content = self.lines.join("\n");
+                content.push('\n');
+                atomic::write(path, content)
+        }
+
+        /// Whether the configuration was read from the legacy single-line form
+        /// and should be written back.
+        pub fn is_migrated(&self) -> bool {
+                self.migrated
+        }
+
+        /// Returns the value of `key`, without its quotes.
+        pub fn get(&self, key: &str) -> Option<String> {
+                self.lines.iter().find_map(|line| {
+                        let (k, v) = parse_line(line)?;
+                        (k == key).then(|| unquote(v))
+                })
+        }
+
+        /// Returns the settings in the order they are written.
+        pub fn settings(&self) -> impl Iterator<Item = (&str, String)> {
+                self.lines
+                        .iter()
+                        .filter_map(|line| parse_line(line))
+                        .map(|(k, v)| (k, unquote(v)))
+        }
+
+        /// Sets `key` to `value`, replacing the line of an existing setting or
+        /// adding one at the end.
+        pub fn set(&mut self, key: &str, value: &str) {
+                let line = setting_line(key, value);
+                match self
+                        .lines
+                        .iter()
+                        .position(|l| parse_line(l).is_some_and(|(k, _)| k == key))
+                {
+                        Some(i) => self.lines[i] = line,
+                        None => self.lines.push(line),
+                }
+        }
+}
+
+/// Path of the user configuration: `$XDG_CONFIG_HOME/tafsk/config`, or
+/// `~/.config/tafsk/config` when `XDG_CONFIG_HOME` isn't set.
+pub fn user_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("tafsk").join("config"))
+}
+
+/// Sets `key` to `value` in the user configuration, creating it if needed.
+pub fn set_user(key: &str, value: &str) -> io::Result<()> {
+        let path = user_path().ok_or_else(|| {
+                io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "Neither XDG_CONFIG_HOME nor HOME is set",
+                )
+        })?;
+        if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+        }
+
+        let mut config = Config::read(&path)?;
+        config.set(key, value);
+        config.write(&path)
+}
+
+/// Checks that `value` is valid for `key`, so that a mistake is reported when
+/// it is made rather than whenever the store is read.
+pub fn validate(key: &str, value: &str) -> Result<(), String> {
+        let result = match key {
+                "timezone" => TimeZone::load(value).map(drop).map_err(|e| e.to_string()),
+                "default_priority" => value
+                        .parse::<u8>()
+                        .map(drop)
+                        .map_err(|_| format!("'{}'. Expected a number between 0 and 255", value)),
+                "id_format" => IdFormat::parse(value).map(drop),
+                "sort" => sort::parse(value).map(drop),
+                "workflow" => Workflow::parse(value).map(drop),
+                "editor" | "format" | "store" => Ok(()),
+                _ if key.strip_prefix("format.")
+                        .is_some_and(|name| !name.is_empty()) =>
+                {
+                        Template::parse(value).map(drop)
+                },
+                _ => {
+                        return Err(format!(
+                                "Unknown setting '{}'. Expected {} or format.NAME",
+                                key,
+                                KEYS.join(", ")
+                        ));
+                },
+        };
+        result.map_err(|e| format!("Invalid {}: {}", key, e))
+}
+
+/// Splits a `key = value` line. Comments and blank lines have no setting.
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+                return None;
+        }
+        let (key, value) = trimmed.split_once('=')?;
+        Some((key.trim(), value.trim()))
+}
+
+/// Writes a setting as a `key = "value"` line.
+pub fn setting_line(key: &str, value: &str) -> String {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("{} = \"{}\"", key, escaped)
+}
+
+/// Removes the double quotes around a value, along with the backslashes
+/// escaping quotes and backslashes inside it. Values without quotes are
+/// returned as written.
+fn unquote(value: &str) -> String {
+        let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+                return value.to_string();
+        };
+
+        let mut unquoted = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+                match c {
+                        '\\' => unquoted.extend(chars.next()),
+                        _ => unquoted.push(c),
+                }
+        }
+        unquoted
+}
@@ -1,3 +1,14 @@
+use crate::tz::TimeZone;
+
+/// Unix timestamp of 0000-01-01T00:00:00Z, the earliest time that dates
+/// written with four-digit years can hold.
+const MIN_TIMESTAMP: i64 = -62_167_219_200;
+
+/// Unix timestamp of 9999-12-31T23:59:59Z, the latest time that dates written
+/// with four-digit years can hold.
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DateTime {
         pub year:   i32,
         pub month:  u8,
@@ -8,6 +19,9 @@ pub struct DateTime {
 }
 
 impl DateTime {
+        /// The date at `timestamp`, in the UTC offset `offset`. Timestamps are
+        /// expected between the years 0000 and 9999: relative dates are
+        /// checked against that range before they get here.
         pub fn new(timestamp: u64, offset: i32) -> Self {
                 let ts = (timestamp as i64) + (offset as i64);
 
@@ -48,4 +62,651 @@ impl DateTime {
                         self.year, self.month, self.day, self.hour, self.minute, self.second
                 )
         }
+
+        /// Formats the date as RFC 3339, given the UTC offset it is in.
+        pub fn to_rfc3339(&self, offset: i32) -> String {
+                format!(
+                        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+                        self.year,
+                        self.month,
+                        self.day,
+                        self.hour,
+                        self.minute,
+                        self.second,
+                        format_offset(offset)
+                )
+        }
+
+        /// Converts the date back to a Unix timestamp, given the UTC offset it
+        /// is in. This is the inverse of [`DateTime::new`].
+        pub fn timestamp(&self, offset: i32) -> i64 {
+                // Shift the year so that it starts in March, like in `new`
+                let y = (self.year as i64) - (if self.month <= 2 { 1 } else { 0 });
+                let era = (if y >= 0 { y } else { y - 399 }) / 400;
+                let yoe = y - era * 400;
+                let m = self.month as i64;
+                let mp = if m > 2 { m - 3 } else { m + 9 };
+                let doy = (153 * mp + 2) / 5 + (self.day as i64) - 1;
+                let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+                let days = era * 146097 + doe - 719468;
+
+                let seconds = (self.hour as i64) * 3600
+                        + (self.minute as i64) * 60
+                        + (self.second as i64);
+                days * 86_400 + seconds - (offset as i64)
+        }
+
+        /// Parses an RFC 3339 time such as `2025-12-19T01:08:09-03:00` into a
+        /// Unix timestamp.
+        pub fn parse_rfc3339(s: &str) -> Option<i64> {
+                let local = s.get(..19)?;
+                let offset = match s.get(19..)? {
+                        "Z" | "z" => 0,
+                        tz => parse_offset(tz)?,
+                };
+                Some(Self::parse(local)?.timestamp(offset))
+        }
+
+        /// Decodes the `YYYYMMDD-HHMMSS` timestamp at the start of a HUID.
+        pub fn from_huid(huid: &str) -> Option<Self> {
+                let stamp = huid.get(..15)?;
+                let (date, time) = stamp.split_once('-')?;
+                if date.len() != 8 || !time.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                }
+
+                Self::from_parts(
+                        date[..4].parse().ok()?,
+                        date[4..6].parse().ok()?,
+                        date[6..].parse().ok()?,
+                        time[..2].parse().ok()?,
+                        time[2..4].parse().ok()?,
+                        time[4..].parse().ok()?,
+                )
+        }
+
+        /// Parses a `YYYY-MM-DD`, `YYYY-MM-DDTHH:MM` or `YYYY-MM-DDTHH:MM:SS`
+        /// local date.
+        pub fn parse(s: &str) -> Option<Self> {
+                let (date, time) = s.split_once('T').unwrap_or((s, "00:00"));
+
+                let mut date_parts = date.split('-');
+                let year = date_parts.next()?;
+                let month = date_parts.next()?;
+                let day = date_parts.next()?;
+                if year.len() != 4
+                        || month.len() != 2
+                        || day.len() != 2
+                        || date_parts.next().is_some()
+                {
+                        return None;
+                }
+
+                let mut time_parts = time.split(':');
+                let hour = time_parts.next()?;
+                let minute = time_parts.next()?;
+                let second = time_parts.next().unwrap_or("00");
+                if hour.len() != 2
+                        || minute.len() != 2
+                        || second.len() != 2
+                        || time_parts.next().is_some()
+                {
+                        return None;
+                }
+
+                Self::from_parts(
+                        year.parse().ok()?,
+                        month.parse().ok()?,
+                        day.parse().ok()?,
+                        hour.parse().ok()?,
+                        minute.parse().ok()?,
+                        second.parse().ok()?,
+                )
+        }
+
+        /// Midnight of the same day.
+        fn start_of_day(&self) -> Self {
+                Self {
+                        hour: 0,
+                        minute: 0,
+                        second: 0,
+                        ..*self
+                }
+        }
+
+        /// The last second of the same day.
+        fn end_of_day(&self) -> Self {
+                Self {
+                        hour: 23,
+                        minute: 59,
+                        second: 59,
+                        ..*self
+                }
+        }
+
+        /// Day of the week, from 0 for Monday to 6 for Sunday.
+        pub(crate) fn weekday(&self) -> i64 {
+                // 1970-01-01 was a Thursday
+                (self.timestamp(0).div_euclid(86_400) + 3).rem_euclid(7)
+        }
+
+        /// Moves the date by `seconds`. Returns `None` if the result is
+        /// outside of the years 0000 to 9999.
+        fn add_seconds(&self, seconds: i64) -> Option<Self> {
+                let timestamp = self.timestamp(0).checked_add(seconds)?;
+                if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&timestamp) {
+                        return None;
+                }
+                Some(Self::new(timestamp as u64, 0))
+        }
+
+        fn add_days(&self, days: i64) -> Option<Self> {
+                self.add_seconds(days.checked_mul(86_400)?)
+        }
+
+        /// Moves the date by whole months, keeping the day unless the target
+        /// month is too short for it, in which case its last day is used.
+        fn add_months(&self, months: i64) -> Option<Self> {
+                let index =
+                        ((self.year as i64) * 12 + (self.month as i64 - 1)).checked_add(months)?;
+                let year = i32::try_from(index.div_euclid(12)).ok()?;
+                if !(0..=9999).contains(&year) {
+                        return None;
+                }
+                let month = (index.rem_euclid(12) + 1) as u8;
+
+                Some(Self {
+                        year,
+                        month,
+                        day: self.day.min(days_in_month(year, month)),
+                        ..*self
+                })
+        }
+
+        pub fn from_parts(
+                year: i32,
+                month: u8,
+                day: u8,
+                hour: u8,
+                minute: u8,
+                second: u8,
+        ) -> Option<Self> {
+                if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+                        return None;
+                }
+                if hour > 23 || minute > 59 || second > 59 {
+                        return None;
+                }
+
+                Some(Self {
+                        year,
+                        month,
+                        day,
+                        hour,
+                        minute,
+                        second,
+                })
+        }
+}
+
+/// Returns the current Unix timestamp.
+pub fn now() -> i64 {
+        std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Formats a Unix timestamp as RFC 3339 with the offset of `tz` at that time.
+pub fn format_rfc3339(timestamp: i64, tz: &TimeZone) -> String {
+        tz.local(timestamp).to_rfc3339(tz.offset_at(timestamp))
+}
+
+/// Describes an amount of seconds in the past, such as `3 days ago`.
+pub fn format_ago(seconds: i64) -> String {
+        #[rustfmt::skip]
+        const UNITS: &[(i64, &str)] = &[
+                (365 * 86_400, "year"),
+                (30 * 86_400,  "month"),
+                (7 * 86_400,   "week"),
+                (86_400,       "day"),
+                (3600,         "hour"),
+                (60,           "minute"),
+        ];
+
+        let Some((count, unit)) = UNITS
+                .iter()
+                .map(|(size, unit)| (seconds / size, unit))
+                .find(|(count, _)| *count > 0)
+        else {
+                return "just now".to_string();
+        };
+        let plural = if count == 1 { "" } else { "s" };
+        format!("{} {}{} ago", count, unit, plural)
+}
+
+/// Describes an amount of seconds in a few characters, such as `5d` or `3mo`.
+pub fn format_age(seconds: i64) -> String {
+        #[rustfmt::skip]
+        const UNITS: &[(i64, &str)] = &[
+                (365 * 86_400, "y"),
+                (30 * 86_400,  "mo"),
+                (86_400,       "d"),
+                (3600,         "h"),
+                (60,           "m"),
+        ];
+
+        UNITS.iter()
+                .map(|(size, unit)| (seconds / size, unit))
+                .find(|(count, _)| *count > 0)
+                .map_or_else(
+                        || "0m".to_string(),
+                        |(count, unit)| format!("{}{}", count, unit),
+                )
+}
+
+/// Parses a time given on the command line into a Unix timestamp.
+///
+/// Besides the local dates of [`DateTime::parse`], where a date alone means
+/// midnight, this accepts times relative to `now`, the current Unix timestamp:
+///
+/// - `HH:MM` for a time of the current day
+/// - `now`, `today`, `tomorrow` and `yesterday`, the latter three at midnight
+/// - `eod` and `eow` for the end of the day and of the week (Sunday)
+/// - a weekday such as `monday`, `fri` or `next fri` for its next occurrence
+///   after today, at midnight
+/// - `in 3d` or `+3d` for an amount of `h`ours, `d`ays, `w`eeks, `mo`nths or
+///   `y`ears from now
+///
+/// Local times are in the timezone `tz`.
+pub fn parse_datetime(input: &str, now: i64, tz: &TimeZone) -> Option<i64> {
+        let input = input.trim();
+        let keyword = input.to_ascii_lowercase();
+        let current = tz.local(now);
+        let today = current.start_of_day();
+
+        #[rustfmt::skip]
+        let when = match keyword.as_str() {
+                "now"       => return Some(now),
+                "today"     => today,
+                "tomorrow"  => today.add_days(1)?,
+                "yesterday" => today.add_days(-1)?,
+                "eod"       => today.end_of_day(),
+                "eow"       => today.add_days(6 - today.weekday())?.end_of_day(),
+                _           => parse_relative(&keyword, &current).or_else(|| DateTime::parse(input))?,
+        };
+
+        Some(tz.timestamp(&when))
+}
+
+/// Parses the lowercase inputs of [`parse_datetime`] that are relative to
+/// `current` but aren't single keywords.
+fn parse_relative(input: &str, current: &DateTime) -> Option<DateTime> {
+        let today = current.start_of_day();
+
+        if let Some(amount) = input
+                .strip_prefix("in ")
+                .or_else(|| input.strip_prefix('+'))
+        {
+                return add_duration(current, amount.trim());
+        }
+
+        if let Some(weekday) = parse_weekday(input.strip_prefix("next ").unwrap_or(input)) {
+                // The next occurrence is a week away when it is today
+                let ahead = (weekday - today.weekday()).rem_euclid(7);
+                return today.add_days(if ahead == 0 { 7 } else { ahead });
+        }
+
+        parse_clock(&today, input)
+}
+
+/// Parses `HH:MM` as a time of the day of `date`.
+fn parse_clock(date: &DateTime, input: &str) -> Option<DateTime> {
+        let (hour, minute) = input.split_once(':')?;
+        if hour.len() != 2 || minute.len() != 2 {
+                return None;
+        }
+
+        DateTime::from_parts(
+                date.year,
+                date.month,
+                date.day,
+                hour.parse().ok()?,
+                minute.parse().ok()?,
+                0,
+        )
+}
+
+/// Parses the name of a weekday, or a prefix of at least three letters, into
+/// its number from 0 for Monday.
+fn parse_weekday(name: &str) -> Option<i64> {
+        const WEEKDAYS: &[&str] = &[
+                "monday",
+                "tuesday",
+                "wednesday",
+                "thursday",
+                "friday",
+                "saturday",
+                "sunday",
+        ];
+
+        if name.len() < 3 {
+                return None;
+        }
+        WEEKDAYS.iter()
+                .position(|day| day.starts_with(name))
+                .map(|n| n as i64)
+}
+
+/// Adds an amount such as `3d`, `2 w` or `1mo` to `date`.
+fn add_duration(date: &DateTime, amount: &str) -> Option<DateTime> {
+        let digits = amount
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(amount.len());
+        let count: i64 = amount[..digits].parse().ok()?;
+
+        #[rustfmt::skip]
+        let moved = match amount[digits..].trim() {
+                "h" | "hour" | "hours"    => date.add_seconds(count.checked_mul(3600)?)?,
+                "d" | "day" | "days"      => date.add_days(count)?,
+                "w" | "week" | "weeks"    => date.add_days(count.checked_mul(7)?)?,
+                "mo" | "month" | "months" => date.add_months(count)?,
+                "y" | "year" | "years"    => date.add_months(count.checked_mul(12)?)?,
+                _                         => return None,
+        };
+        Some(moved)
+}
+
+/// Parses a `+HH:MM` or `-HH:MM` offset into seconds.
+pub fn parse_offset(s: &str) -> Option<i32> {
+        const TZ: &str = "+HH:MM";
+        if s.len() != TZ.len() {
+                return None;
+        }
+
+        const COLON_SEP: char = ':';
+        if s.chars().nth(3)? != COLON_SEP {
+                return None;
+        }
+
+        let sign = s.chars().next()?;
+        if sign != '+' && sign != '-' {
+                return None;
+        }
+
+        // `get` rather than slicing: the input may hold multibyte characters
+        let (hours, minutes) = (s.get(1..3)?, s.get(4..6)?);
+        if !hours
+                .bytes()
+                .chain(minutes.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+                return None;
+        }
+        #[rustfmt::skip]
+        let hours: i32   = hours.parse().ok()?;
+        let minutes: i32 = minutes.parse().ok()?;
+
+        const SECS_IN_HOUR: i32 = 3600;
+        const SECS_IN_MINUTE: i32 = 60;
+
+        let offset_in_secs = (hours * SECS_IN_HOUR) + (minutes * SECS_IN_MINUTE);
+        #[rustfmt::skip]
+        let final_offset = if sign == '-' { -offset_in_secs } else { offset_in_secs };
+
+        Some(final_offset)
+}
+
+/// Formats an offset in seconds as `+HH:MM`.
+pub fn format_offset(offset: i32) -> String {
+        let sign = if offset < 0 { '-' } else { '+' };
+        let minutes = offset.unsigned_abs() / 60;
+        format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+pub fn days_in_month(year: i32, month: u8) -> u8 {
+        let is_leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        match month {
+                2 if is_leap => 29,
+                2 => 28,
+                4 | 6 | 9 | 11 => 30,
+                _ => 31,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        const OFFSET: i32 = -3 * 3600;
+        const TZ: TimeZone = TimeZone::Fixed(OFFSET);
+
+        /// Resolves `input` at the local time `now` and formats the result as a
+        /// local time, to keep expectations readable.
+        fn resolve(input: &str, now: &str) -> Option<String> {
+                let now = DateTime::parse(now)?.timestamp(OFFSET);
+                let when = parse_datetime(input, now, &TZ)?;
+                Some(DateTime::new(when as u64, OFFSET).to_rfc3339(OFFSET)[..19].to_string())
+        }
+
+        fn local(s: &str) -> Option<String> {
+                Some(s.to_string())
+        }
+
+        #[test]
+        fn parses_rfc3339_times() {
+                assert_eq!(
+                        DateTime::parse_rfc3339("2025-12-19T01:08:09-03:00"),
+                        Some(1_766_117_289)
+                );
+                assert_eq!(
+                        DateTime::parse_rfc3339("2025-12-19T04:08:09Z"),
+                        Some(1_766_117_289)
+                );
+                // Hand-edited front matter
+                for input in [
+                        "2026-01-01T00:00:00é12:0",
+                        "2026-01-01T00:00:00+1é:00",
+                        "2026-01-01T00:00:00x05:00",
+                        "2026-01-01T00:00:00-+1:00",
+                        "2026-01-01T00:00:00+05:0é",
+                        "2026-01-01T00:00:00",
+                ] {
+                        assert_eq!(DateTime::parse_rfc3339(input), None, "{input}");
+                }
+        }
+
+        #[test]
+        fn timestamp_is_the_inverse_of_new() {
+                for ts in [0, 951_782_400, 1_709_164_800, 1_766_116_089, 4_102_444_799] {
+                        let dt = DateTime::new(ts, OFFSET);
+                        assert_eq!(dt.timestamp(OFFSET), ts as i64);
+                }
+        }
+
+        #[test]
+        fn parses_absolute_dates_and_times() {
+                let now = "2025-12-19T10:30";
+                assert_eq!(resolve("2026-12-01", now), local("2026-12-01T00:00:00"));
+                assert_eq!(
+                        resolve("2026-12-01T09:00", now),
+                        local("2026-12-01T09:00:00")
+                );
+                assert_eq!(resolve("17:00", now), local("2025-12-19T17:00:00"));
+                assert_eq!(resolve("now", now), local("2025-12-19T10:30:00"));
+                assert_eq!(resolve("2026-02-29", now), None);
+                assert_eq!(resolve("25:00", now), None);
+        }
+
+        #[test]
+        fn parses_day_keywords() {
+                let now = "2025-12-19T10:30";
+                assert_eq!(resolve("today", now), local("2025-12-19T00:00:00"));
+                assert_eq!(resolve("TOMORROW", now), local("2025-12-20T00:00:00"));
+                assert_eq!(resolve("yesterday", now), local("2025-12-18T00:00:00"));
+                assert_eq!(resolve("eod", now), local("2025-12-19T23:59:59"));
+                // 2025-12-19 is a Friday
+                assert_eq!(resolve("eow", now), local("2025-12-21T23:59:59"));
+        }
+
+        #[test]
+        fn rolls_days_over_months_and_years() {
+                assert_eq!(
+                        resolve("tomorrow", "2025-12-31T23:00"),
+                        local("2026-01-01T00:00:00")
+                );
+                assert_eq!(
+                        resolve("yesterday", "2026-01-01T01:00"),
+                        local("2025-12-31T00:00:00")
+                );
+                assert_eq!(
+                        resolve("tomorrow", "2025-04-30T12:00"),
+                        local("2025-05-01T00:00:00")
+                );
+                // 2025-12-31 is a Wednesday
+                assert_eq!(
+                        resolve("eow", "2025-12-31T12:00"),
+                        local("2026-01-04T23:59:59")
+                );
+                assert_eq!(
+                        resolve("in 3d", "2025-12-30T08:15"),
+                        local("2026-01-02T08:15:00")
+                );
+                assert_eq!(
+                        resolve("+2w", "2025-12-20T08:15"),
+                        local("2026-01-03T08:15:00")
+                );
+                assert_eq!(
+                        resolve("in 36h", "2025-12-31T12:00"),
+                        local("2026-01-02T00:00:00")
+                );
+        }
+
+        #[test]
+        fn handles_leap_years() {
+                assert_eq!(
+                        resolve("tomorrow", "2024-02-28T10:00"),
+                        local("2024-02-29T00:00:00")
+                );
+                assert_eq!(
+                        resolve("tomorrow", "2023-02-28T10:00"),
+                        local("2023-03-01T00:00:00")
+                );
+                assert_eq!(
+                        resolve("tomorrow", "2000-02-28T10:00"),
+                        local("2000-02-29T00:00:00")
+                );
+                assert_eq!(
+                        resolve("tomorrow", "2100-02-28T10:00"),
+                        local("2100-03-01T00:00:00")
+                );
+                assert_eq!(
+                        resolve("in 1d", "2024-02-29T10:00"),
+                        local("2024-03-01T10:00:00")
+                );
+                assert_eq!(
+                        resolve("+1y", "2024-02-29T10:00"),
+                        local("2025-02-28T10:00:00")
+                );
+                assert_eq!(
+                        resolve("+4y", "2024-02-29T10:00"),
+                        local("2028-02-29T10:00:00")
+                );
+        }
+
+        #[test]
+        fn clamps_months_to_their_last_day() {
+                assert_eq!(
+                        resolve("in 1mo", "2024-01-31T09:00"),
+                        local("2024-02-29T09:00:00")
+                );
+                assert_eq!(
+                        resolve("in 1mo", "2023-01-31T09:00"),
+                        local("2023-02-28T09:00:00")
+                );
+                assert_eq!(
+                        resolve("+1 month", "2025-03-31T09:00"),
+                        local("2025-04-30T09:00:00")
+                );
+                assert_eq!(
+                        resolve("+2mo", "2025-11-15T09:00"),
+                        local("2026-01-15T09:00:00")
+                );
+                assert_eq!(
+                        resolve("in 13 months", "2025-12-31T09:00"),
+                        local("2027-01-31T09:00:00")
+                );
+        }
+
+        #[test]
+        fn parses_weekdays() {
+                // 2025-12-31 is a Wednesday
+                let now = "2025-12-31T12:00";
+                assert_eq!(resolve("friday", now), local("2026-01-02T00:00:00"));
+                assert_eq!(resolve("next fri", now), local("2026-01-02T00:00:00"));
+                assert_eq!(resolve("monday", now), local("2026-01-05T00:00:00"));
+                assert_eq!(resolve("wed", now), local("2026-01-07T00:00:00"));
+                assert_eq!(resolve("thu", now), local("2026-01-01T00:00:00"));
+                assert_eq!(resolve("fr", now), None);
+        }
+
+        #[test]
+        fn uses_the_local_day() {
+                // 01:30 UTC is still the previous day at -03:00
+                let now = DateTime::parse("2026-01-01T01:30").map(|dt| dt.timestamp(0));
+                let today = now.and_then(|ts| parse_datetime("today", ts, &TZ));
+                assert_eq!(
+                        today,
+                        DateTime::parse("2025-12-31").map(|dt| dt.timestamp(OFFSET))
+                );
+        }
+
+        #[test]
+        fn describes_elapsed_time() {
+                assert_eq!(format_ago(30), "just now");
+                assert_eq!(format_ago(60), "1 minute ago");
+                assert_eq!(format_ago(2 * 3600 + 59), "2 hours ago");
+                assert_eq!(format_ago(3 * 86_400), "3 days ago");
+                assert_eq!(format_ago(15 * 86_400), "2 weeks ago");
+                assert_eq!(format_ago(95 * 86_400), "3 months ago");
+                assert_eq!(format_ago(800 * 86_400), "2 years ago");
+        }
+
+        #[test]
+        fn abbreviates_ages() {
+                assert_eq!(format_age(-5), "0m");
+                assert_eq!(format_age(59), "0m");
+                assert_eq!(format_age(2 * 3600 + 1800), "2h");
+                assert_eq!(format_age(5 * 86_400), "5d");
+                assert_eq!(format_age(29 * 86_400), "29d");
+                assert_eq!(format_age(95 * 86_400), "3mo");
+                assert_eq!(format_age(400 * 86_400), "1y");
+        }
+
+        #[test]
+        fn rejects_malformed_input() {
+                let now = "2025-12-19T10:30";
+                for input in [
+                        "",
+                        "soon",
+                        "in",
+                        "in d",
+                        "+3x",
+                        "in -3d",
+                        "next",
+                        "2025-13-01",
+                        "in 999999999999999d",
+                        "in 9223372036854775807h",
+                        "+9999999w",
+                        "+9999999y",
+                        "+8000y",
+                        "in 99999999999999999999mo",
+                ] {
+                        assert_eq!(resolve(input, now), None, "{input}");
+                }
+
+                // Dates must fit in four-digit years
+                assert_eq!(resolve("+7974y", now), local("9999-12-19T10:30:00"));
+                assert_eq!(resolve("tomorrow", "9999-12-31T10:30"), None);
+                assert_eq!(resolve("yesterday", "0000-01-01T10:30"), None);
+        }
 }
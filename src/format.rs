@@ -0,0 +1,397 @@
+use std::path::PathBuf;
+
+use crate::commands::Task;
+use crate::config::IdFormat;
+use crate::datetime::format_ago;
+use crate::store::{
+        Store,
+        short_id,
+};
+use crate::workflow::Workflow;
+
+/// Version of the JSON output. Bumped whenever a field is removed or changes
+/// meaning; new fields may be added without a bump.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Templates that are always available by name.
+#[rustfmt::skip]
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+        ("quickfix", "{path}:{line}:{id}: {markers} {title}"),
+        ("plain",    "{id} {priority:>3} {status:<6} {title:<50.50} {tags}"),
+];
+
+pub enum Format {
+        Json,
+        Jsonl,
+        Template(Template),
+}
+
+impl Format {
+        /// Resolves the value of `--format`: `json`, `jsonl`, the name of a
+        /// built-in template, the name of a template from the store
+        /// configuration, or an inline template.
+        pub fn resolve(spec: Option<&str>, store: &Store) -> Result<Self, lexopt::Error> {
+                let spec = spec.unwrap_or("quickfix");
+                match spec {
+                        "json" => return Ok(Format::Json),
+                        "jsonl" => return Ok(Format::Jsonl),
+                        _ => {},
+                }
+
+                let named = store
+                        .setting(&format!("format.{}", spec))
+                        .map_err(|e| lexopt::Error::Custom(e.into()))?
+                        .or_else(|| {
+                                BUILTIN_TEMPLATES
+                                        .iter()
+                                        .find(|(name, _)| *name == spec)
+                                        .map(|(_, template)| template.to_string())
+                        });
+
+                let template = match named {
+                        Some(template) => template,
+                        None if spec.contains('{') => spec.to_string(),
+                        None => {
+                                return Err(lexopt::Error::Custom(
+                                        format!("Unknown format '{}'. Expected json, jsonl, a template name or a template", spec).into(),
+                                ));
+                        },
+                };
+
+                Template::parse(&template)
+                        .map(Format::Template)
+                        .map_err(|e| {
+                                lexopt::Error::Custom(
+                                        format!("Invalid template '{}': {}", template, e).into(),
+                                )
+                        })
+        }
+}
+
+/// Fields that can be used in templates.
+const FIELDS: &[&str] = &[
+        "id",
+        "huid",
+        "path",
+        "line",
+        "status",
+        "priority",
+        "tags",
+        "title",
+        "created",
+        "age",
+        "closed",
+        "due",
+        "parent",
+        "progress",
+        "blocked_by",
+        "markers",
+];
+
+enum Segment {
+        Literal(String),
+        Field {
+                name:      String,
+                align:     char,
+                width:     usize,
+                precision: Option<usize>,
+        },
+}
+
+/// A line layout such as `{id} {priority:>3} {title:.40}`.
+///
+/// Fields are written as `{name}` or `{name:[<|>|^][WIDTH][.MAX]}`, which pads
+/// the value to `WIDTH` characters and truncates it to `MAX` characters.
+/// Literal braces are written `{{` and `}}`.
+pub struct Template {
+        segments: Vec<Segment>,
+}
+
+impl Template {
+        pub fn parse(s: &str) -> Result<Self, String> {
+                let mut segments = Vec::new();
+                let mut literal = String::new();
+                let mut chars = s.chars().peekable();
+
+                while let Some(c) = chars.next() {
+                        match c {
+                                '{' if chars.peek() == Some(&'{') => {
+                                        chars.next();
+                                        literal.push('{');
+                                },
+                                '}' if chars.peek() == Some(&'}') => {
+                                        chars.next();
+                                        literal.push('}');
+                                },
+                                '{' => {
+                                        let mut spec = String::new();
+                                        loop {
+                                                match chars.next() {
+                                                        Some('}') => break,
+                                                        Some(next) => spec.push(next),
+                                                        None => {
+                                                                return Err(
+                                                                        "unclosed '{'".to_string()
+                                                                );
+                                                        },
+                                                }
+                                        }
+                                        if !literal.is_empty() {
+                                                segments.push(Segment::Literal(std::mem::take(
+                                                        &mut literal,
+                                                )));
+                                        }
+                                        segments.push(parse_field(&spec)?);
+                                },
+                                '}' => return Err("unmatched '}'".to_string()),
+                                c => literal.push(c),
+                        }
+                }
+
+                if !literal.is_empty() {
+                        segments.push(Segment::Literal(literal));
+                }
+                Ok(Self { segments })
+        }
+
+        pub fn render(&self, entry: &Entry) -> String {
+                let mut out = String::new();
+                for segment in &self.segments {
+                        match segment {
+                                Segment::Literal(text) => out.push_str(text),
+                                Segment::Field {
+                                        name,
+                                        align,
+                                        width,
+                                        precision,
+                                } => {
+                                        let value = entry.field(name);
+                                        let value: String = match precision {
+                                                Some(max) => value.chars().take(*max).collect(),
+                                                None => value,
+                                        };
+                                        let pad = width.saturating_sub(value.chars().count());
+                                        #[rustfmt::skip]
+                                        let (left, right) = match align {
+                                                '>' => (pad, 0),
+                                                '^' => (pad / 2, pad - pad / 2),
+                                                _   => (0, pad),
+                                        };
+                                        out.push_str(&" ".repeat(left));
+                                        out.push_str(&value);
+                                        out.push_str(&" ".repeat(right));
+                                },
+                        }
+                }
+                // Padding the last field shouldn't leave trailing spaces behind
+                out.truncate(out.trim_end_matches(' ').len());
+                out
+        }
+}
+
+fn parse_field(spec: &str) -> Result<Segment, String> {
+        let (name, format) = spec.split_once(':').unwrap_or((spec, ""));
+        let name = name.trim();
+        if !FIELDS.contains(&name) {
+                return Err(format!(
+                        "unknown field '{}'. Expected one of: {}",
+                        name,
+                        FIELDS.join(", ")
+                ));
+        }
+
+        let (align, format) = match format.chars().next() {
+                Some(c @ ('<' | '>' | '^')) => (c, &format[1..]),
+                _ => ('<', format),
+        };
+        let (width, precision) = format
+                .split_once('.')
+                .map_or((format, None), |(w, p)| (w, Some(p)));
+
+        let invalid = || format!("invalid format '{}' for field '{}'", spec, name);
+        let width = if width.is_empty() {
+                0
+        } else {
+                width.parse().map_err(|_| invalid())?
+        };
+        let precision = precision
+                .map(|p| p.parse().map_err(|_| invalid()))
+                .transpose()?;
+
+        Ok(Segment::Field {
+                name: name.to_string(),
+                align,
+                width,
+                precision,
+        })
+}
+
+/// A task as printed by `list`, along with what is known from its location.
+pub struct Entry<'a> {
+        pub huid:       &'a str,
+        /// HUID of the parent of a subtask.
+        pub parent:     Option<&'a str>,
+        pub path:       PathBuf,
+        pub task:       &'a Task,
+        /// Creation time in RFC 3339, from the front matter or else decoded
+        /// from the HUID.
+        pub created:    Option<String>,
+        /// Time since creation, such as `5d`.
+        pub age:        Option<String>,
+        /// Closing time in RFC 3339.
+        pub closed:     Option<String>,
+        /// Due time in RFC 3339.
+        pub due:        Option<String>,
+        /// Current Unix timestamp, for relative times.
+        pub now:        i64,
+        /// Whether the markers include the age.
+        pub show_age:   bool,
+        pub workflow:   &'a Workflow,
+        pub id_format:  IdFormat,
+        /// Indentation of the title, for `--tree`.
+        pub depth:      usize,
+        /// Closed and total number of subtasks.
+        pub progress:   Option<(usize, usize)>,
+        /// HUIDs of the dependencies that are still open.
+        pub blocked_by: Vec<&'a str>,
+        /// Line of the task file to point editors at.
+        pub line:       usize,
+}
+
+impl Entry<'_> {
+        pub fn id(&self) -> &str {
+                self.format_id(self.huid)
+        }
+
+        /// Shortens `huid` as configured by `id_format`.
+        fn format_id<'b>(&self, huid: &'b str) -> &'b str {
+                match self.id_format {
+                        IdFormat::Hash => short_id(huid),
+                        IdFormat::Huid => huid,
+                }
+        }
+
+        fn blocked_by_ids(&self) -> String {
+                let ids: Vec<&str> = self
+                        .blocked_by
+                        .iter()
+                        .map(|huid| self.format_id(huid))
+                        .collect();
+                ids.join(",")
+        }
+
+        /// Returns the value of a template field.
+        #[rustfmt::skip]
+        fn field(&self, name: &str) -> String {
+                match name {
+                        "id"       => self.id().to_string(),
+                        "huid"     => self.huid.to_string(),
+                        "path"     => self.path.display().to_string(),
+                        "line"     => self.line.to_string(),
+                        "status"   => self.task.status.to_string(),
+                        "priority" => self.task.priority.to_string(),
+                        "tags"     => self.task.tags.join(","),
+                        "title"    => format!("{}{}", "  ".repeat(self.depth), self.task.title),
+                        "parent"   => self.parent.unwrap_or_default().to_string(),
+                        "progress" => self.progress.map(|(closed, total)| format!("{}/{}", closed, total)).unwrap_or_default(),
+                        "created"  => self.created.clone().unwrap_or_default(),
+                        "age"      => self.age.clone().unwrap_or_default(),
+                        "closed"   => self.closed.clone().unwrap_or_default(),
+                        "due"      => self.due.clone().unwrap_or_default(),
+                        "blocked_by" => self.blocked_by_ids(),
+                        "markers"  => self.markers(),
+                        _          => String::new(),
+                }
+        }
+
+        /// The `[KEY: VALUE]` annotations of the quickfix output.
+        fn markers(&self) -> String {
+                let tags = self.task.tags.join(",");
+                let mut parts = vec![];
+
+                parts.push(format!("[PRIORITY: {:>3}]", self.task.priority));
+                if self.show_age
+                        && let Some(age) = &self.age
+                {
+                        parts.push(format!("[AGE: {}]", age))
+                }
+                if let Some(due) = &self.due {
+                        // Minutes are precise enough to glance at
+                        parts.push(format!("[DUE: {}]", due[..16].replace('T', " ")))
+                }
+                if !self.workflow.is_initial(self.task.status.as_str()) {
+                        parts.push(format!(
+                                "[STATUS: {}]",
+                                self.task.status.as_str().to_uppercase()
+                        ))
+                }
+                if let Some(closed) = self.task.closed {
+                        parts.push(format!("[CLOSED: {}]", format_ago(self.now - closed)))
+                }
+                if let Some((closed, total)) = self.progress {
+                        parts.push(format!("[{}/{}]", closed, total))
+                }
+                if !self.blocked_by.is_empty() {
+                        parts.push(format!("[BLOCKED BY: {}]", self.blocked_by_ids()))
+                }
+                if !tags.is_empty() {
+                        parts.push(format!("[TAGS: {}]", tags))
+                }
+
+                parts.join(" ")
+        }
+
+        pub fn to_json(&self) -> String {
+                let tags: Vec<String> = self.task.tags.iter().map(|t| json_string(t)).collect();
+                let created = self
+                        .created
+                        .as_deref()
+                        .map_or("null".to_string(), json_string);
+                let closed = self
+                        .closed
+                        .as_deref()
+                        .map_or("null".to_string(), json_string);
+                let due = self.due.as_deref().map_or("null".to_string(), json_string);
+                let parent = self.parent.map_or("null".to_string(), json_string);
+                let depends: Vec<String> =
+                        self.task.depends.iter().map(|d| json_string(d)).collect();
+
+                format!(
+                        "{{\"version\":{},\"id\":{},\"huid\":{},\"path\":{},\"line\":{},\"status\":{},\"priority\":{},\"tags\":[{}],\"title\":{},\"created\":{},\"closed\":{},\"due\":{},\"parent\":{},\"depends\":[{}],\"body\":{}}}",
+                        SCHEMA_VERSION,
+                        json_string(self.id()),
+                        json_string(self.huid),
+                        json_string(&self.path.to_string_lossy()),
+                        self.line,
+                        json_string(&self.task.status.to_string()),
+                        self.task.priority,
+                        tags.join(","),
+                        json_string(&self.task.title),
+                        created,
+                        closed,
+                        due,
+                        parent,
+                        depends.join(","),
+                        json_string(&self.task.body),
+                )
+        }
+}
+
+/// Quotes and escapes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('"');
+        for c in s.chars() {
+                match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        '\r' => out.push_str("\\r"),
+                        '\t' => out.push_str("\\t"),
+                        c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => out.push(c),
+                }
+        }
+        out.push('"');
+        out
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use crate::commands::Task;
+use crate::store::{
+        huid_of,
+        parent_of,
+        short_id,
+};
+use crate::workflow::Workflow;
+
+/// Renders `tasks` as a Graphviz DOT document.
+///
+/// Every task is a node labelled with its title, short ID, priority and
+/// status, and filled with the color of its status. Dashed edges go from
+/// parents to their subtasks, and solid edges from each dependency to the
+/// task waiting for it. Edges to tasks that aren't in `tasks` are left out.
+pub fn render(tasks: &[&(String, Task)], workflow: &Workflow) -> String {
+        let shown: HashSet<&str> = tasks.iter().map(|(path, _)| huid_of(path)).collect();
+
+        let mut lines = vec![
+                "digraph tasks {".to_string(),
+                "    rankdir=LR;".to_string(),
+                "    node [shape=box, style=\"rounded,filled\", fontname=\"sans-serif\"];"
+                        .to_string(),
+        ];
+
+        for (path, task) in tasks {
+                let label = format!(
+                        "{}\n{}  P{}  {}",
+                        task.title,
+                        short_id(path),
+                        task.priority,
+                        task.status.as_str().to_uppercase()
+                );
+                lines.push(format!(
+                        "    {} [label={}, fillcolor=\"{}\"];",
+                        dot_string(huid_of(path)),
+                        dot_string(&label),
+                        status_color(workflow, task.status.as_str())
+                ));
+        }
+
+        for (path, task) in tasks {
+                if let Some(parent) = parent_of(path).map(huid_of)
+                        && shown.contains(parent)
+                {
+                        lines.push(format!(
+                                "    {} -> {} [style=dashed, color=\"gray50\"];",
+                                dot_string(parent),
+                                dot_string(huid_of(path))
+                        ));
+                }
+                for dependency in task.depends.iter().filter(|d| shown.contains(d.as_str())) {
+                        lines.push(format!(
+                                "    {} -> {};",
+                                dot_string(dependency),
+                                dot_string(huid_of(path))
+                        ));
+                }
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+}
+
+/// Light colors, so that labels stay readable: white for new tasks, green for
+/// closed ones, blue for those in between, and red for unknown states.
+fn status_color(workflow: &Workflow, status: &str) -> &'static str {
+        if workflow.find(status).is_none() {
+                "#ffcdd2"
+        } else if workflow.is_closed(status) {
+                "#c8e6c9"
+        } else if workflow.is_initial(status) {
+                "#ffffff"
+        } else {
+                "#bbdefb"
+        }
+}
+
+/// Quotes and escapes `s` as a DOT string. Line breaks become `\n`, which
+/// centers the lines of a label.
+fn dot_string(s: &str) -> String {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('"');
+        for c in s.chars() {
+                match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        _ => out.push(c),
+                }
+        }
+        out.push('"');
+        out
+}
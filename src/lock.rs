@@ -0,0 +1,111 @@
+use std::fs::{
+        File,
+        OpenOptions,
+        TryLockError,
+};
+use std::io::{
+        Read,
+        Seek,
+        Write,
+};
+use std::path::Path;
+use std::time::{
+        Duration,
+        Instant,
+};
+use std::{
+        env,
+        io,
+        thread,
+};
+
+/// How long to wait for another process to release a store when
+/// `TAFSK_LOCK_TIMEOUT` isn't set.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a locked store is checked while waiting for it.
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An advisory lock on a store, held by commands that change it so that they
+/// don't overwrite each other's changes. Read-only commands don't take it.
+///
+/// The lock is an exclusive file lock on the `.lock` file at the root of the
+/// store, which also holds the ID of the process that owns it for error
+/// messages. The operating system releases it when the guard is dropped or
+/// the process ends, however it ends, so there are no stale locks to detect
+/// and take over. The file itself stays in place.
+pub struct Lock {
+        _file: File,
+}
+
+impl Lock {
+        /// Locks the store at `root`, waiting for `TAFSK_LOCK_TIMEOUT` seconds
+        /// if another process holds it.
+        pub fn acquire(root: &Path) -> io::Result<Self> {
+                let path = root.join(".lock");
+                let deadline = Instant::now() + timeout()?;
+
+                // Not truncated here: the ID in it belongs to the holder
+                let mut file = OpenOptions::new()
+                        .read(true)
+                        .write(true)
+                        .create(true)
+                        .truncate(false)
+                        .open(&path)?;
+
+                loop {
+                        match file.try_lock() {
+                                Ok(()) => break,
+                                Err(TryLockError::WouldBlock) => {},
+                                Err(TryLockError::Error(e)) => return Err(e),
+                        }
+
+                        if Instant::now() >= deadline {
+                                let holder = holder(&mut file)
+                                        .map_or("another process".to_string(), |pid| {
+                                                format!("process {}", pid)
+                                        });
+                                return Err(io::Error::new(
+                                        io::ErrorKind::TimedOut,
+                                        format!(
+                                                "Store {} is locked by {}. Try again once it is done.",
+                                                root.display(),
+                                                holder
+                                        ),
+                                ));
+                        }
+                        thread::sleep(RETRY_INTERVAL);
+                }
+
+                file.set_len(0)?;
+                file.rewind()?;
+                writeln!(file, "{}", std::process::id())?;
+                Ok(Self { _file: file })
+        }
+}
+
+/// The ID of the process holding the lock of `file`, if it has written it yet.
+fn holder(file: &mut File) -> Option<u32> {
+        let mut content = String::new();
+        file.rewind().ok()?;
+        file.read_to_string(&mut content).ok()?;
+        content.trim().parse().ok()
+}
+
+fn timeout() -> io::Result<Duration> {
+        let Some(value) = env::var_os("TAFSK_LOCK_TIMEOUT") else {
+                return Ok(DEFAULT_TIMEOUT);
+        };
+        value.to_str()
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                .ok_or_else(|| {
+                        io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!(
+                                        "Invalid TAFSK_LOCK_TIMEOUT '{}'. Expected a number of seconds",
+                                        value.to_string_lossy()
+                                ),
+                        )
+                })
+}
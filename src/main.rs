@@ -2,16 +2,22 @@
 #![allow(rustdoc::broken_intra_doc_links)]
 #![doc = include_str!("../README.md")]
 
+mod atomic;
 mod commands;
+mod config;
 mod datetime;
+mod format;
+mod graph;
+mod lock;
+mod query;
+mod sort;
 mod store;
+mod tz;
+mod workflow;
 
 use std::process::ExitCode;
 
-use lexopt::prelude::*;
-
 use crate::commands::{
-        ListArgs,
         Task,
         print_global_help,
 };
@@ -24,37 +30,61 @@ fn main() -> ExitCode {
         ExitCode::SUCCESS
 }
 
+/// Arguments that are dispatched here when they come first. Anything else is
+/// left for the implicit `list` to parse.
+const COMMANDS: &[&str] = &[
+        "-h",
+        "--help",
+        "-V",
+        "--version",
+        "add",
+        "config",
+        "depend",
+        "done",
+        "edit",
+        "graph",
+        "init",
+        "list",
+        "modify",
+        "mv",
+        "reopen",
+        "status",
+];
+
 #[rustfmt::skip]
 fn run() -> Result<(), lexopt::Error> {
         let mut parser = lexopt::Parser::from_env();
 
-        let Some(arg) = parser.next()? else { return Task::list(ListArgs::default(), &mut parser) };
+        let command = parser
+                .try_raw_args()
+                .and_then(|mut raw| raw.next_if(|arg| arg.to_str().is_some_and(|a| COMMANDS.contains(&a))))
+                .and_then(|arg| arg.into_string().ok());
 
-        match arg {
+        match command.as_deref() {
                 // Global flags
-                Short('h') | Long("help")    => { print_global_help(); Ok(()) },
-                Short('V') | Long("version") => { println!("tafsk {}", env!("CARGO_PKG_VERSION")); Ok(()) },
+                Some("-h" | "--help")    => { print_global_help(); Ok(()) },
+                Some("-V" | "--version") => { println!("tafsk {}", env!("CARGO_PKG_VERSION")); Ok(()) },
 
                 // Explicit subcommands
-                Value(val) if val == "add"  => Task::add(&mut parser),
-                Value(val) if val == "done" => Task::done(&mut parser),
-                Value(val) if val == "init" => Task::init(&mut parser),
-                Value(val) if val == "list" => Task::list(ListArgs::default(), &mut parser),
+                Some("add")    => Task::add(&mut parser),
+                Some("config") => Task::config(&mut parser),
+                Some("depend") => Task::depend(&mut parser),
+                Some("done")   => Task::done(&mut parser),
+                Some("edit")   => Task::edit(&mut parser),
+                Some("graph")  => Task::graph(&mut parser),
+                Some("init")   => Task::init(&mut parser),
+                Some("list")   => Task::list(&mut parser),
+                Some("modify") => Task::modify(&mut parser),
+                Some("mv")     => Task::mv(&mut parser),
+                Some("reopen") => Task::reopen(&mut parser),
+                Some("status") => Task::status(&mut parser),
 
                 // Implicit `list`
-                flags => {
-                        let mut args = ListArgs::default();
-                        match flags {
-                                // TASK(20251219-010809.f3fe84e5)
-                                Short('c') | Long("closed") => args.show_closed = true,
-                                Short('g') | Long("global") => args.show_global = true,
-                                _ => {
-                                        print_global_help();
-                                        println!();
-                                        return Err(flags.unexpected());
-                                }
+                _ => Task::list(&mut parser).inspect_err(|e| {
+                        if matches!(e, lexopt::Error::UnexpectedOption(_) | lexopt::Error::UnexpectedArgument(_)) {
+                                print_global_help();
+                                println!();
                         }
-                        Task::list(args, &mut parser)
-                },
+                }),
         }
 }
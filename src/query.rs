@@ -0,0 +1,318 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::commands::Task;
+use crate::datetime::DateTime;
+
+// expr       = and ("or" and)*
+// and        = unary ("and" unary)*
+// unary      = "not" unary | "(" expr ")" | comparison
+// comparison = FIELD OP VALUE
+// OP         = ":" | "=" | "!=" | "<" | "<=" | ">" | ">=" | "~"
+// VALUE      = WORD | '"' ... '"'
+
+#[derive(Debug)]
+pub enum Expr {
+        Or(Box<Expr>, Box<Expr>),
+        And(Box<Expr>, Box<Expr>),
+        Not(Box<Expr>),
+        Priority(Op, u8),
+        Tag(Op, String),
+        Status(Op, String),
+        Title(Op, String),
+        /// The flag is set when the value has no time, in which case only the
+        /// date of creation is compared.
+        Created(Op, DateTime, bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+        Eq,
+        Ne,
+        Lt,
+        Le,
+        Gt,
+        Ge,
+        Match,
+}
+
+impl Op {
+        #[rustfmt::skip]
+        fn compare<T: Ord>(self, a: &T, b: &T) -> bool {
+                match self {
+                        Op::Eq | Op::Match => a == b,
+                        Op::Ne             => a != b,
+                        Op::Lt             => a < b,
+                        Op::Le             => a <= b,
+                        Op::Gt             => a > b,
+                        Op::Ge             => a >= b,
+                }
+        }
+}
+
+impl Expr {
+        /// Evaluates the expression against `task`, whose creation time is
+        /// `created` in the timezone of its store, when it is known.
+        pub fn matches(&self, task: &Task, created: Option<&DateTime>) -> bool {
+                match self {
+                        Expr::Or(a, b) => a.matches(task, created) || b.matches(task, created),
+                        Expr::And(a, b) => a.matches(task, created) && b.matches(task, created),
+                        Expr::Not(e) => !e.matches(task, created),
+                        Expr::Priority(op, p) => op.compare(&task.priority, p),
+                        Expr::Tag(op, tag) => match op {
+                                Op::Ne => !task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+                                Op::Match => task.tags.iter().any(|t| contains_ignore_case(t, tag)),
+                                _ => task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+                        },
+                        Expr::Status(op, status) => {
+                                let is_equal = task.status.to_string().eq_ignore_ascii_case(status);
+                                if *op == Op::Ne { !is_equal } else { is_equal }
+                        },
+                        Expr::Title(op, title) => match op {
+                                Op::Ne => !task.title.eq_ignore_ascii_case(title),
+                                Op::Match => contains_ignore_case(&task.title, title),
+                                _ => task.title.eq_ignore_ascii_case(title),
+                        },
+                        Expr::Created(op, when, date_only) => {
+                                let Some(created) = created else {
+                                        return false;
+                                };
+                                if *date_only {
+                                        let date = |d: &DateTime| (d.year, d.month, d.day);
+                                        op.compare(&date(created), &date(when))
+                                } else {
+                                        op.compare(created, when)
+                                }
+                        },
+                }
+        }
+
+        /// Checks whether the expression looks at the status of tasks.
+        pub fn mentions_status(&self) -> bool {
+                match self {
+                        Expr::Or(a, b) | Expr::And(a, b) => {
+                                a.mentions_status() || b.mentions_status()
+                        },
+                        Expr::Not(e) => e.mentions_status(),
+                        Expr::Status(..) => true,
+                        _ => false,
+                }
+        }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+        haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[derive(Debug)]
+pub struct QueryError {
+        input:   String,
+        pos:     usize,
+        message: String,
+}
+
+impl Display for QueryError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let column = self.input[..self.pos].chars().count();
+                writeln!(f, "invalid query: {}", self.message)?;
+                writeln!(f, "    {}", self.input)?;
+                write!(f, "    {}^", " ".repeat(column))
+        }
+}
+
+impl Error for QueryError {}
+
+/// Parses a query expression such as `priority>=50 and tag:work`.
+pub fn parse(input: &str) -> Result<Expr, QueryError> {
+        let mut parser = Parser { input, pos: 0 };
+        let expr = parser.expr()?;
+
+        parser.skip_whitespace();
+        if parser.pos < input.len() {
+                return Err(parser.error(parser.pos, "expected 'and', 'or' or end of query"));
+        }
+        Ok(expr)
+}
+
+struct Parser<'a> {
+        input: &'a str,
+        pos:   usize,
+}
+
+impl Parser<'_> {
+        fn error(&self, pos: usize, message: &str) -> QueryError {
+                QueryError {
+                        input: self.input.to_string(),
+                        pos,
+                        message: message.to_string(),
+                }
+        }
+
+        fn rest(&self) -> &str {
+                &self.input[self.pos..]
+        }
+
+        fn skip_whitespace(&mut self) {
+                let rest = self.rest();
+                self.pos += rest.len() - rest.trim_start().len();
+        }
+
+        /// Consumes `keyword` if it is the next word, ignoring case.
+        fn keyword(&mut self, keyword: &str) -> bool {
+                self.skip_whitespace();
+                let rest = self.rest();
+                let is_match = rest
+                        .get(..keyword.len())
+                        .is_some_and(|w| w.eq_ignore_ascii_case(keyword))
+                        && rest[keyword.len()..]
+                                .chars()
+                                .next()
+                                .is_none_or(|c| c.is_whitespace() || c == '(');
+                if is_match {
+                        self.pos += keyword.len();
+                }
+                is_match
+        }
+
+        fn expr(&mut self) -> Result<Expr, QueryError> {
+                let mut lhs = self.and()?;
+                while self.keyword("or") {
+                        lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+                }
+                Ok(lhs)
+        }
+
+        fn and(&mut self) -> Result<Expr, QueryError> {
+                let mut lhs = self.unary()?;
+                while self.keyword("and") {
+                        lhs = Expr::And(Box::new(lhs), Box::new(self.unary()?));
+                }
+                Ok(lhs)
+        }
+
+        fn unary(&mut self) -> Result<Expr, QueryError> {
+                if self.keyword("not") {
+                        return Ok(Expr::Not(Box::new(self.unary()?)));
+                }
+
+                self.skip_whitespace();
+                if self.rest().starts_with('(') {
+                        let open = self.pos;
+                        self.pos += 1;
+                        let expr = self.expr()?;
+                        self.skip_whitespace();
+                        if !self.rest().starts_with(')') {
+                                return Err(self.error(open, "unclosed parenthesis"));
+                        }
+                        self.pos += 1;
+                        return Ok(expr);
+                }
+
+                self.comparison()
+        }
+
+        fn comparison(&mut self) -> Result<Expr, QueryError> {
+                let field_pos = self.pos;
+                let field_len = self
+                        .rest()
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(self.rest().len());
+                if field_len == 0 {
+                        return Err(self.error(field_pos, "expected a field name"));
+                }
+                let field = self.input[field_pos..field_pos + field_len].to_lowercase();
+                self.pos += field_len;
+
+                self.skip_whitespace();
+                let op_pos = self.pos;
+                let op = self
+                        .op()
+                        .ok_or_else(|| self.error(op_pos, "expected an operator"))?;
+
+                self.skip_whitespace();
+                let value_pos = self.pos;
+                let value = self.value()?;
+                let unsupported =
+                        || self.error(op_pos, &format!("operator not supported for '{}'", field));
+
+                #[rustfmt::skip]
+                let expr = match field.as_str() {
+                        "priority" => {
+                                if op == Op::Match { return Err(unsupported()) }
+                                let p = value.parse().map_err(|_| self.error(value_pos, "expected a priority between 0 and 255"))?;
+                                Expr::Priority(op, p)
+                        },
+                        "tag" | "tags" => {
+                                if !matches!(op, Op::Eq | Op::Ne | Op::Match) { return Err(unsupported()) }
+                                Expr::Tag(op, value)
+                        },
+                        "status" => {
+                                if !matches!(op, Op::Eq | Op::Ne) { return Err(unsupported()) }
+                                Expr::Status(op, value)
+                        },
+                        "title" => {
+                                if !matches!(op, Op::Eq | Op::Ne | Op::Match) { return Err(unsupported()) }
+                                Expr::Title(op, value)
+                        },
+                        "created" => {
+                                if op == Op::Match { return Err(unsupported()) }
+                                let when = DateTime::parse(&value).ok_or_else(|| self.error(value_pos, "expected a date like 2025-12-01 or 2025-12-01T09:00"))?;
+                                Expr::Created(op, when, !value.contains('T'))
+                        },
+                        _ => return Err(self.error(field_pos, &format!("unknown field '{}'", field))),
+                };
+
+                Ok(expr)
+        }
+
+        fn op(&mut self) -> Option<Op> {
+                #[rustfmt::skip]
+                const OPS: &[(&str, Op)] = &[
+                        (">=", Op::Ge), ("<=", Op::Le), ("!=", Op::Ne),
+                        (">",  Op::Gt), ("<",  Op::Lt), ("=",  Op::Eq),
+                        (":",  Op::Eq), ("~",  Op::Match),
+                ];
+
+                self.skip_whitespace();
+                let (token, op) = OPS
+                        .iter()
+                        .find(|(token, _)| self.rest().starts_with(token))?;
+                self.pos += token.len();
+                Some(*op)
+        }
+
+        fn value(&mut self) -> Result<String, QueryError> {
+                self.skip_whitespace();
+                let start = self.pos;
+
+                if let Some(quoted) = self.rest().strip_prefix('"') {
+                        let mut value = String::new();
+                        let mut chars = quoted.char_indices();
+                        while let Some((i, c)) = chars.next() {
+                                match c {
+                                        '"' => {
+                                                self.pos += i + 2;
+                                                return Ok(value);
+                                        },
+                                        '\\' => {
+                                                if let Some((_, escaped)) = chars.next() {
+                                                        value.push(escaped);
+                                                }
+                                        },
+                                        _ => value.push(c),
+                                }
+                        }
+                        return Err(self.error(start, "unterminated string"));
+                }
+
+                let len = self
+                        .rest()
+                        .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                        .unwrap_or(self.rest().len());
+                if len == 0 {
+                        return Err(self.error(start, "expected a value"));
+                }
+                self.pos += len;
+                Ok(self.input[start..self.pos].to_string())
+        }
+}
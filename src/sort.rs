@@ -0,0 +1,117 @@
+use std::cmp::Ordering;
+
+use crate::commands::{
+        Task,
+        created_at,
+};
+use crate::tz::TimeZone;
+
+/// Order used when neither `--sort` nor the store configuration set one.
+pub const DEFAULT_SORT: &str = "-priority";
+
+#[derive(Clone, Copy)]
+enum Field {
+        Priority,
+        Created,
+        Closed,
+        Due,
+        Tag,
+        Title,
+}
+
+/// One key of a `--sort` specification such as `priority,-created,title`.
+pub struct SortKey {
+        field:      Field,
+        descending: bool,
+}
+
+/// Parses a comma-separated list of sort keys. Keys sort in ascending order,
+/// or in descending order when prefixed with `-`.
+pub fn parse(spec: &str) -> Result<Vec<SortKey>, String> {
+        spec.split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| {
+                        let (name, descending) = match key.strip_prefix('-') {
+                                Some(name) => (name, true),
+                                None => (key.strip_prefix('+').unwrap_or(key), false),
+                        };
+
+                        #[rustfmt::skip]
+                        let field = match name {
+                                "priority" => Field::Priority,
+                                "created"  => Field::Created,
+                                "closed"   => Field::Closed,
+                                "due"      => Field::Due,
+                                "tag"      => Field::Tag,
+                                "title"    => Field::Title,
+                                _          => return Err(format!(
+                                        "Unknown sort key '{}'. Expected priority, created, closed, due, tag or title",
+                                        name
+                                )),
+                        };
+
+                        Ok(SortKey { field, descending })
+                })
+                .collect()
+}
+
+/// Compares two `(path, task)` pairs of a store in `timezone` by `keys`,
+/// falling back to the path so that the order is always the same.
+///
+/// Tasks without a value for a key, such as open tasks for `closed`, come
+/// last whatever the direction.
+pub fn compare(
+        keys: &[SortKey],
+        a: &(String, Task),
+        b: &(String, Task),
+        timezone: &TimeZone,
+) -> Ordering {
+        for key in keys {
+                let ordering = match key.field {
+                        Field::Priority => Some(a.1.priority.cmp(&b.1.priority)),
+                        Field::Created => compare_present(
+                                created_at(&a.0, &a.1, timezone),
+                                created_at(&b.0, &b.1, timezone),
+                        ),
+                        Field::Closed => compare_present(a.1.closed, b.1.closed),
+                        Field::Due => compare_present(a.1.due, b.1.due),
+                        Field::Tag => compare_present(first_tag(&a.1), first_tag(&b.1)),
+                        Field::Title => {
+                                Some(a.1.title.to_lowercase().cmp(&b.1.title.to_lowercase()))
+                        },
+                };
+
+                let ordering = match ordering {
+                        Some(o) if key.descending => o.reverse(),
+                        Some(o) => o,
+                        None => missing_last(&a.1, &b.1, key.field),
+                };
+
+                if ordering != Ordering::Equal {
+                        return ordering;
+                }
+        }
+
+        a.0.cmp(&b.0)
+}
+
+/// Compares two optional values. Returns `None` when either is missing.
+fn compare_present<T: Ord>(a: Option<T>, b: Option<T>) -> Option<Ordering> {
+        Some(a?.cmp(&b?))
+}
+
+fn missing_last(a: &Task, b: &Task, field: Field) -> Ordering {
+        let has_value = |task: &Task| match field {
+                Field::Closed => task.closed.is_some(),
+                Field::Due => task.due.is_some(),
+                Field::Tag => !task.tags.is_empty(),
+                Field::Priority | Field::Created | Field::Title => true,
+        };
+        has_value(b).cmp(&has_value(a))
+}
+
+/// The alphabetically first tag of a task.
+fn first_tag(task: &Task) -> Option<String> {
+        task.tags.iter().map(|t| t.to_lowercase()).min()
+}
@@ -3,15 +3,39 @@ use std::hash::{
         Hash,
         Hasher,
 };
-use std::path::PathBuf;
+use std::path::{
+        Path,
+        PathBuf,
+};
 use std::str::FromStr;
 use std::{
         fs,
         io,
 };
 
-use crate::commands::Task;
-use crate::datetime::DateTime;
+use crate::atomic;
+use crate::commands::{
+        Task,
+        set_front_matter,
+};
+use crate::config::{
+        Config,
+        IdFormat,
+};
+use crate::datetime::{
+        DateTime,
+        format_rfc3339,
+};
+use crate::lock::Lock;
+use crate::tz::TimeZone;
+use crate::workflow::Workflow;
+
+/// Priority of new tasks when the configuration doesn't set one.
+pub const DEFAULT_PRIORITY: u8 = 10;
+
+/// How many hashes `create_task` tries before giving up on finding a HUID
+/// that isn't taken.
+const MAX_HUID_ATTEMPTS: u32 = 100;
 
 pub struct Store {
         pub root: PathBuf,
@@ -24,135 +48,290 @@ impl Store {
 
         pub fn new(is_global: bool) -> io::Result<Self> {
                 if is_global {
-                        if let Ok(dir) = std::env::var("TAFSK_STORE_DIR") {
-                                return Ok(Self {
-                                        root: PathBuf::from(dir),
-                                });
-                        } else {
-                                return Err(io::Error::new(
+                        return match global_root()? {
+                                Some(root) => Ok(Self { root }),
+                                None => Err(io::Error::new(
                                         io::ErrorKind::NotFound,
-                                        "TAFSK_STORE_DIR is not set",
-                                ));
-                        }
+                                        "No global store. Set TAFSK_STORE_DIR or the store setting of the user configuration.",
+                                )),
+                        };
+                }
+
+                if let Some(store) = Self::local()? {
+                        return Ok(store);
                 }
 
+                if let Some(root) = global_root()? {
+                        return Ok(Self { root });
+                }
+
+                Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "No task store found. Run 'tafsk init' to create one.",
+                ))
+        }
+
+        /// Finds the closest `tasks` directory in the current directory or
+        /// one of its parents.
+        pub fn local() -> io::Result<Option<Self>> {
                 let mut current_dir = std::env::current_dir()?;
                 loop {
                         let tasks_dir = current_dir.join("tasks");
                         if tasks_dir.exists() {
-                                return Ok(Self { root: tasks_dir });
+                                return Ok(Some(Self { root: tasks_dir }));
                         }
                         if !current_dir.pop() {
-                                break;
+                                return Ok(None);
                         }
                 }
+        }
 
-                if let Ok(dir) = std::env::var("TAFSK_STORE_DIR") {
-                        return Ok(Self {
-                                root: PathBuf::from(dir),
-                        });
-                }
+        /// Locks the store for a command that changes it, creating the root
+        /// directory if needed. The lock is held until the guard is dropped.
+        ///
+        /// A legacy configuration is rewritten into `key = value` settings
+        /// once the lock is held, so that commands that only read the store
+        /// never write to it.
+        pub fn lock(&self) -> io::Result<Lock> {
+                fs::create_dir_all(&self.root)?;
+                let lock = Lock::acquire(&self.root)?;
 
-                Err(io::Error::new(
-                        io::ErrorKind::NotFound,
-                        "No task store found. Run 'tafsk init' to create one.",
-                ))
+                let config_path = self.config_path();
+                let config = Config::read(&config_path)?;
+                if config.is_migrated() {
+                        config.write(&config_path)?;
+                }
+                Ok(lock)
         }
 
         /// Ensures the root directory and configuration exist.
-        /// Returns the configured timezone offset in seconds.
-        fn init(&self) -> io::Result<i32> {
+        /// Returns the configured timezone.
+        fn init(&self) -> io::Result<TimeZone> {
                 fs::create_dir_all(&self.root)?;
 
-                let config_path = self.root.join(".config");
+                let config_path = self.config_path();
                 if config_path.exists() {
-                        let content = fs::read_to_string(&config_path)?;
-
-                        for line in content.lines() {
-                                let trimmed = line.trim();
-                                if trimmed.is_empty() || trimmed.starts_with('#') {
-                                        continue;
-                                }
-                                return parse_offset(trimmed).ok_or_else(|| {
-                                        io::Error::new(
-                                                io::ErrorKind::InvalidData,
-                                                format!("Invalid timezone in config: {}", trimmed),
-                                        )
-                                });
-                        }
-
-                        Err(io::Error::new(
-                                io::ErrorKind::InvalidData,
-                                "Config file found but contains no timezone",
-                        ))
+                        self.timezone()
                 } else {
-                        let utc_offset = 0;
-                        let timezone = "+00:00";
+                        Config::new("+00:00").write(&config_path)?;
+                        Ok(TimeZone::Fixed(0))
+                }
+        }
 
-                        let content = [
-                                "# Configuration for tafsk store",
-                                "# Timezone offset (e.g. +09:30, -05:00)",
-                                timezone,
-                        ];
+        fn config_path(&self) -> PathBuf {
+                self.root.join(".config")
+        }
+
+        /// Reads the configuration. A legacy one that holds nothing but the
+        /// timezone is read as `key = value` settings, but only rewritten by
+        /// [`Store::lock`].
+        pub fn config(&self) -> io::Result<Config> {
+                Config::read(&self.config_path())
+        }
 
-                        fs::write(&config_path, content.join("\n"))?;
-                        Ok(utc_offset)
+        /// Reads a `key = value` setting from the configuration, or from the
+        /// user configuration when the store doesn't have it.
+        pub fn setting(&self, key: &str) -> io::Result<Option<String>> {
+                match self.config()?.get(key) {
+                        Some(value) => Ok(Some(value)),
+                        None => Ok(Config::user()?.get(key)),
                 }
         }
 
-        /// Updates the timezone configuration.
+        /// Sets `key` to `value` in the configuration, creating the store if
+        /// needed.
+        pub fn set_setting(&self, key: &str, value: &str) -> io::Result<()> {
+                self.init()?;
+                let mut config = self.config()?;
+                config.set(key, value);
+                config.write(&self.config_path())
+        }
+
+        /// Reads the configured timezone without creating
+        /// anything. Stores without configuration are in UTC.
+        pub fn timezone(&self) -> io::Result<TimeZone> {
+                let Some(timezone) = self.setting("timezone")? else {
+                        return Ok(TimeZone::Fixed(0));
+                };
+                TimeZone::load(&timezone).map_err(|e| invalid_setting("timezone", e))
+        }
+
+        /// Reads the workflow from the configuration.
+        pub fn workflow(&self) -> io::Result<Workflow> {
+                let Some(workflow) = self.setting("workflow")? else {
+                        return Ok(Workflow::default());
+                };
+                Workflow::parse(&workflow).map_err(|e| invalid_setting("workflow", e))
+        }
+
+        /// Reads the priority of new tasks from the configuration.
+        pub fn default_priority(&self) -> io::Result<u8> {
+                let Some(priority) = self.setting("default_priority")? else {
+                        return Ok(DEFAULT_PRIORITY);
+                };
+                priority.parse().map_err(|_| {
+                        invalid_setting(
+                                "default_priority",
+                                format!("'{}'. Expected a number between 0 and 255", priority),
+                        )
+                })
+        }
+
+        /// Returns the editor command: the `editor` setting of the store or of
+        /// the user, then $EDITOR, then vi.
+        pub fn editor(&self) -> io::Result<String> {
+                Ok(self.setting("editor")?
+                        .or_else(|| std::env::var("EDITOR").ok())
+                        .unwrap_or_else(|| "vi".to_string()))
+        }
+
+        /// Reads how `list` shows task IDs from the configuration.
+        pub fn id_format(&self) -> io::Result<IdFormat> {
+                let Some(format) = self.setting("id_format")? else {
+                        return Ok(IdFormat::default());
+                };
+                IdFormat::parse(&format).map_err(|e| invalid_setting("id_format", e))
+        }
+
+        /// Updates the timezone configuration, keeping the other settings.
         pub fn update_config(&self, timezone: &str) -> io::Result<()> {
-                if parse_offset(timezone).is_none() {
-                        return Err(io::Error::new(
-                                io::ErrorKind::InvalidInput,
-                                "Invalid timezone format. Expected +HH:MM or -HH:MM",
-                        ));
-                }
+                TimeZone::load(timezone)?;
 
                 if !self.root.exists() {
                         fs::create_dir_all(&self.root)?;
                 }
 
-                let config_path = self.root.join(".config");
-                let content = [
-                        "# Configuration for tafsk store",
-                        "# Timezone offset (e.g. +09:30, -05:00)",
-                        timezone,
-                ];
-
-                fs::write(&config_path, content.join("\n"))?;
-                Ok(())
+                let config_path = self.config_path();
+                let mut config = if config_path.exists() {
+                        Config::read(&config_path)?
+                } else {
+                        Config::new(timezone)
+                };
+                config.set("timezone", timezone);
+                config.write(&config_path)
         }
 
-        /// Saves a new task to disk.
-        /// Returns the folder name of the newly created task.
-        pub fn create_task(&self, content: &str) -> io::Result<String> {
-                let offset = self.init()?;
-
-                let mut hasher = DefaultHasher::new();
-                content.hash(&mut hasher);
-                let hash_val = hasher.finish();
-                let hash_str = format!("{:x}", hash_val);
-                let short_hash = &hash_str[..8];
+        /// Saves a new task to disk, recording its creation time in the
+        /// `created:` front matter key. Subtasks are saved inside the
+        /// directory of their `parent`, given by its path in the store.
+        /// Returns the path of the newly created task in the store.
+        pub fn create_task(&self, content: &str, parent: Option<&str>) -> io::Result<String> {
+                let timezone = self.init()?;
 
                 let now = std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)
-                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                // .as_secs();
-
-                let timestamp = DateTime::new(now.as_secs(), offset).format();
-                let folder_name = format!("{}.{}", timestamp, short_hash);
+                        .map_err(io::Error::other)?
+                        .as_secs() as i64;
+
+                let timestamp = timezone.local(now).format();
+                let task_file =
+                        set_front_matter(content, "created", &format_rfc3339(now, &timezone));
+
+                // Tasks with the same content created in the same second, or
+                // whose hashes happen to collide, get the next hash that is free
+                for attempt in 0..MAX_HUID_ATTEMPTS {
+                        let huid = format!("{}.{}", timestamp, huid_hash(content, attempt));
+                        let folder_name = match parent {
+                                Some(parent) => format!("{}/{}", parent, huid),
+                                None => huid,
+                        };
+                        let task_dir = self.root.join(&folder_name);
+
+                        match fs::create_dir(&task_dir) {
+                                Ok(()) => {},
+                                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                                Err(e) => return Err(e),
+                        }
+                        if let Err(e) = atomic::write(&task_dir.join("TASK.md"), &task_file) {
+                                fs::remove_dir(&task_dir).ok();
+                                return Err(e);
+                        }
+                        return Ok(folder_name);
+                }
 
-                let task_dir = self.root.join(&folder_name);
-                let task_file = task_dir.join("TASK.md");
+                Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!(
+                                "No free HUID for the task after {} attempts",
+                                MAX_HUID_ATTEMPTS
+                        ),
+                ))
+        }
 
-                fs::create_dir_all(&task_dir)?;
-                fs::write(&task_file, content)?;
+        /// Moves the task at `task_path`, along with its attachments and
+        /// subtasks, into `target`, under the task at `parent` if any. Both
+        /// stores may be the same.
+        /// Returns the new path of the task in `target`.
+        pub fn move_task(
+                &self,
+                task_path: &str,
+                target: &Store,
+                parent: Option<&str>,
+        ) -> io::Result<String> {
+                let huid = huid_of(task_path);
+                let new_path = match parent {
+                        Some(parent) => format!("{}/{}", parent, huid),
+                        None => huid.to_string(),
+                };
+
+                let source = self.root.join(task_path);
+                let destination = target.root.join(&new_path);
+                if destination.exists() {
+                        let message = if same_file(&source, &destination) {
+                                format!("Task {} is already at {}", huid, destination.display())
+                        } else {
+                                format!(
+                                        "Can't move task {}: a task with the same HUID exists at {}",
+                                        huid,
+                                        destination.display()
+                                )
+                        };
+                        return Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+                }
+                // IDs are resolved by HUID, so a copy nested anywhere else in
+                // the target store would make them ambiguous
+                if target.root.exists()
+                        && let Some((existing, _)) =
+                                target.list_tasks()?.iter().find(|(path, _)| {
+                                        huid_of(path) == huid
+                                                && !same_file(&source, &target.root.join(path))
+                                })
+                {
+                        return Err(io::Error::new(
+                                io::ErrorKind::AlreadyExists,
+                                format!(
+                                        "Can't move task {}: a task with the same HUID exists at {}",
+                                        huid,
+                                        target.root.join(existing).display()
+                                ),
+                        ));
+                }
+                if let Some(parent) = parent
+                        && same_file(&self.root, &target.root)
+                        && (parent == task_path || parent.starts_with(&format!("{}/", task_path)))
+                {
+                        return Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("Can't move task {} under itself", huid),
+                        ));
+                }
 
-                Ok(folder_name)
+                target.init()?;
+                match fs::rename(&source, &destination) {
+                        Ok(()) => {},
+                        // Renaming only works within a file system
+                        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                                copy_dir(&source, &destination)?;
+                                fs::remove_dir_all(&source)?;
+                        },
+                        Err(e) => return Err(e),
+                }
+                Ok(new_path)
         }
 
-        /// Lists all tasks in the store, sorted by folder name.
+        /// Lists all tasks in the store, subtasks included, sorted by their
+        /// path in the store, such as `20251219-010809.f3fe84e5` or
+        /// `20251219-010809.f3fe84e5/20251220-093000.0badcafe` for a subtask.
         pub fn list_tasks(&self) -> io::Result<Vec<(String, Task)>> {
                 let mut tasks = Vec::new();
 
@@ -161,61 +340,207 @@ impl Store {
                         return Ok(tasks);
                 }
 
-                let entries = fs::read_dir(&self.root)?;
+                let workflow = self.workflow()?;
+                self.read_tasks(&self.root, "", &workflow, &mut tasks)?;
+
+                tasks.sort_by(|a, b| a.0.cmp(&b.0));
+                Ok(tasks)
+        }
 
-                for entry in entries {
+        /// Reads the tasks in `dir`, whose path in the store is `prefix`, and
+        /// then their subtasks.
+        fn read_tasks(
+                &self,
+                dir: &Path,
+                prefix: &str,
+                workflow: &Workflow,
+                tasks: &mut Vec<(String, Task)>,
+        ) -> io::Result<()> {
+                for entry in fs::read_dir(dir)? {
                         let entry = entry?;
                         let path = entry.path();
 
-                        if path.is_dir()
-                                && let Some(folder_name) = path.file_name().and_then(|s| s.to_str())
-                                && !folder_name.starts_with('.')
-                        {
-                                let task_file = path.join("TASK.md");
-                                if task_file.exists() {
-                                        let content = fs::read_to_string(&task_file)?;
-                                        match Task::from_str(&content) {
-                                                Ok(task) => {
-                                                        tasks.push((folder_name.to_string(), task));
-                                                },
-                                                Err(e) => {
-                                                        eprintln!(
-                                                                "Warning: Failed to parse task in {}: {}",
-                                                                folder_name, e
-                                                        );
-                                                },
+                        let Some(folder_name) = path.file_name().and_then(|s| s.to_str()) else {
+                                continue;
+                        };
+                        let task_file = path.join("TASK.md");
+                        if !path.is_dir() || folder_name.starts_with('.') || !task_file.exists() {
+                                continue;
+                        }
+
+                        let task_path = format!("{}{}", prefix, folder_name);
+                        let content = fs::read_to_string(&task_file)?;
+                        match Task::from_str(&content) {
+                                Ok(task) => {
+                                        // Still listed, but its age and creation time are unknown
+                                        if let Err(e) = parse_huid(folder_name) {
+                                                eprintln!(
+                                                        "Warning: Malformed HUID {}: {}",
+                                                        task_path, e
+                                                );
+                                        }
+                                        if workflow.find(task.status.as_str()).is_none() {
+                                                eprintln!(
+                                                        "Warning: Unknown state '{}' in task {}. Expected one of: {}",
+                                                        task.status,
+                                                        task_path,
+                                                        workflow.states()
+                                                                .collect::<Vec<_>>()
+                                                                .join(", ")
+                                                );
                                         }
-                                }
+                                        tasks.push((task_path.clone(), task));
+                                },
+                                Err(e) => {
+                                        eprintln!(
+                                                "Warning: Failed to parse task in {}: {}",
+                                                task_path, e
+                                        );
+                                },
                         }
+
+                        self.read_tasks(&path, &format!("{}/", task_path), workflow, tasks)?;
                 }
+                Ok(())
+        }
+}
 
-                tasks.sort_by(|a, b| a.0.cmp(&b.0));
-                Ok(tasks)
+/// The hash component of a HUID: the first 8 hex digits of the hash of the
+/// task content. Retries after a collision also hash their attempt number.
+fn huid_hash(content: &str, attempt: u32) -> String {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        if attempt > 0 {
+                attempt.hash(&mut hasher);
         }
+        let hash_str = format!("{:x}", hasher.finish());
+        hash_str[..8].to_string()
 }
 
-fn parse_offset(s: &str) -> Option<i32> {
-        const TZ: &str = "+HH:MM";
-        if s.len() != TZ.len() {
-                return None;
+/// Whether `a` and `b` are the same existing file or directory.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+        match (fs::canonicalize(a), fs::canonicalize(b)) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
         }
+}
 
-        const COLON_SEP: char = ':';
-        if s.chars().nth(3)? != COLON_SEP {
-                return None;
+/// Copies the directory `from`, and everything in it, to `to`.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                let destination = to.join(entry.file_name());
+                if entry.file_type()?.is_dir() {
+                        copy_dir(&entry.path(), &destination)?;
+                } else {
+                        fs::copy(entry.path(), &destination)?;
+                }
         }
+        Ok(())
+}
 
-        let sign = s.chars().next()?;
-        #[rustfmt::skip]
-        let hours: i32   = s[1..3].parse().ok()?;
-        let minutes: i32 = s[4..6].parse().ok()?;
+/// Returns the HUID of a task from its path in the store.
+pub fn huid_of(task_path: &str) -> &str {
+        task_path
+                .rsplit_once('/')
+                .map_or(task_path, |(_, huid)| huid)
+}
+
+/// Returns the path in the store of the parent of a subtask.
+pub fn parent_of(task_path: &str) -> Option<&str> {
+        task_path.rsplit_once('/').map(|(parent, _)| parent)
+}
 
-        const SECS_IN_HOUR: i32 = 3600;
-        const SECS_IN_MINUTE: i32 = 60;
+/// The global store: $TAFSK_STORE_DIR, or the `store` setting of the user
+/// configuration, where a leading `~/` stands for the home directory.
+fn global_root() -> io::Result<Option<PathBuf>> {
+        if let Ok(dir) = std::env::var("TAFSK_STORE_DIR") {
+                return Ok(Some(PathBuf::from(dir)));
+        }
 
-        let offset_in_secs = (hours * SECS_IN_HOUR) + (minutes * SECS_IN_MINUTE);
-        #[rustfmt::skip]
-        let final_offset = if sign == '-' { -offset_in_secs } else { offset_in_secs };
+        let Some(dir) = Config::user()?.get("store") else {
+                return Ok(None);
+        };
+        let root = match (dir.strip_prefix("~/"), std::env::var_os("HOME")) {
+                (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+                _ => PathBuf::from(dir),
+        };
+        Ok(Some(root))
+}
+
+fn invalid_setting(key: &str, e: impl std::fmt::Display) -> io::Error {
+        io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {} in config: {}", key, e),
+        )
+}
 
-        Some(final_offset)
+/// Returns the short ID of a task: the hash component of its HUID.
+pub fn short_id(task_path: &str) -> &str {
+        let huid = huid_of(task_path);
+        huid.split_once('.').map_or(huid, |(_, hash)| hash)
+}
+
+/// Decodes the creation time of a HUID such as `20251219-010809.f3fe84e5`,
+/// in the timezone the task was created in.
+pub fn parse_huid(huid: &str) -> Result<DateTime, String> {
+        let Some((stamp, hash)) = huid.split_once('.') else {
+                return Err("expected YYYYMMDD-HHMMSS.HASH".to_string());
+        };
+        if hash.len() != 8 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("hash '{}' is not 8 hexadecimal digits", hash));
+        }
+        if stamp.len() != 15 {
+                return Err(format!("timestamp '{}' is not YYYYMMDD-HHMMSS", stamp));
+        }
+        DateTime::from_huid(stamp)
+                .ok_or_else(|| format!("timestamp '{}' is not a valid date", stamp))
+}
+
+/// Resolves a task ID to an index into `tasks`.
+///
+/// An ID is either a full HUID, the path of the task in the store, or a unique
+/// prefix of the HUID or of its hash component. Positions in the store are
+/// not IDs: they change whenever a task is added.
+pub fn find_task(tasks: &[(String, Task)], id: &str) -> io::Result<usize> {
+        if let Some(idx) = tasks
+                .iter()
+                .position(|(folder, _)| folder == id || huid_of(folder) == id)
+        {
+                return Ok(idx);
+        }
+
+        let matches: Vec<usize> = tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, (folder, _))| {
+                        !id.is_empty()
+                                && (huid_of(folder).starts_with(id)
+                                        || short_id(folder).starts_with(id))
+                })
+                .map(|(i, _)| i)
+                .collect();
+
+        match matches.as_slice() {
+                [idx] => Ok(*idx),
+                [] => Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("No task matches ID '{}'.", id),
+                )),
+                _ => {
+                        let candidates: Vec<String> = matches
+                                .iter()
+                                .map(|&i| format!("    {} {}", tasks[i].0, tasks[i].1.title))
+                                .collect();
+                        Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!(
+                                        "ID '{}' is ambiguous. Candidates:\n{}",
+                                        id,
+                                        candidates.join("\n")
+                                ),
+                        ))
+                },
+        }
 }
@@ -0,0 +1,554 @@
+use std::path::{
+        Component,
+        Path,
+        PathBuf,
+};
+use std::{
+        env,
+        fs,
+        io,
+};
+
+use crate::datetime::{
+        DateTime,
+        days_in_month,
+        parse_offset,
+};
+
+/// Where zone files are read from when `TZDIR` isn't set.
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+
+/// The timezone of a store: either a fixed UTC offset or an IANA zone whose
+/// offset changes over time.
+#[derive(Debug)]
+pub enum TimeZone {
+        Fixed(i32),
+        Zone(Zone),
+}
+
+impl TimeZone {
+        /// Resolves a `+HH:MM` offset or an IANA zone name such as
+        /// `America/Sao_Paulo`, reading the zone from `TZDIR` or
+        /// `/usr/share/zoneinfo`.
+        pub fn load(name: &str) -> io::Result<Self> {
+                if let Some(offset) = parse_offset(name) {
+                        return Ok(TimeZone::Fixed(offset));
+                }
+
+                let unknown = || {
+                        io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!(
+                                        "Unknown timezone '{}'. Expected +HH:MM, -HH:MM or a zone name such as Europe/Berlin",
+                                        name
+                                ),
+                        )
+                };
+
+                // Zone names are relative paths that stay inside the zone directory
+                let relative = Path::new(name);
+                if name.is_empty()
+                        || !relative
+                                .components()
+                                .all(|c| matches!(c, Component::Normal(_)))
+                {
+                        return Err(unknown());
+                }
+
+                let dir = env::var_os("TZDIR")
+                        .map_or_else(|| PathBuf::from(ZONEINFO_DIR), PathBuf::from);
+                let data = match fs::read(dir.join(relative)) {
+                        Ok(data) => data,
+                        Err(e) if matches!(
+                                e.kind(),
+                                io::ErrorKind::NotFound | io::ErrorKind::IsADirectory
+                        ) =>
+                        {
+                                return Err(unknown());
+                        },
+                        Err(e) => return Err(e),
+                };
+
+                Zone::parse(&data).map(TimeZone::Zone).ok_or_else(|| {
+                        io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("Invalid zone file for timezone '{}'", name),
+                        )
+                })
+        }
+
+        /// The UTC offset in seconds at the Unix timestamp `ts`.
+        pub fn offset_at(&self, ts: i64) -> i32 {
+                match self {
+                        TimeZone::Fixed(offset) => *offset,
+                        TimeZone::Zone(zone) => zone.offset_at(ts),
+                }
+        }
+
+        /// Converts a local time to a Unix timestamp.
+        ///
+        /// Local times that happen twice, when clocks are turned back, resolve
+        /// to the first one. Local times that are skipped, when clocks are
+        /// turned forward, are read with the offset from before the change.
+        pub fn timestamp(&self, local: &DateTime) -> i64 {
+                let wall = local.timestamp(0);
+                let before = self.offset_at(wall - 86_400);
+                let after = self.offset_at(wall + 86_400);
+
+                for offset in [before, after] {
+                        let ts = wall - offset as i64;
+                        if self.offset_at(ts) == offset {
+                                return ts;
+                        }
+                }
+                wall - before as i64
+        }
+
+        /// The local time at the Unix timestamp `ts`.
+        pub fn local(&self, ts: i64) -> DateTime {
+                DateTime::new(ts as u64, self.offset_at(ts))
+        }
+}
+
+/// A zone read from a TZif file, as described in RFC 8536.
+#[derive(Debug)]
+pub struct Zone {
+        /// Offset before the first transition.
+        initial:     i32,
+        /// Times at which the offset changes, and the offset from then on.
+        transitions: Vec<(i64, i32)>,
+        /// Rule for the times after the last transition.
+        rule:        Option<Rule>,
+}
+
+impl Zone {
+        fn parse(data: &[u8]) -> Option<Self> {
+                let mut reader = Reader { data, pos: 0 };
+                let header = reader.header()?;
+
+                let (header, time_size) = if header.version >= b'2' {
+                        // Skip the data block with 32-bit times in favor of the 64-bit one
+                        reader.skip(header.data_len(4))?;
+                        (reader.header()?, 8)
+                } else {
+                        (header, 4)
+                };
+
+                let mut times = Vec::with_capacity(header.timecnt);
+                for _ in 0..header.timecnt {
+                        times.push(reader.int(time_size)?);
+                }
+                let indices = reader.take(header.timecnt)?.to_vec();
+                let mut offsets = Vec::with_capacity(header.typecnt);
+                for _ in 0..header.typecnt {
+                        offsets.push(reader.int(4)? as i32);
+                        // Skip the DST flag and the designation index
+                        reader.skip(2)?;
+                }
+                reader.skip(header.charcnt
+                        + header.leapcnt * (time_size + 4)
+                        + header.isstdcnt
+                        + header.isutcnt)?;
+
+                let transitions = times
+                        .into_iter()
+                        .zip(indices)
+                        .map(|(at, index)| Some((at, *offsets.get(index as usize)?)))
+                        .collect::<Option<Vec<_>>>()?;
+
+                // The footer is a POSIX TZ string between newlines
+                let rule = if time_size == 8 {
+                        Rule::from_footer(reader.rest())
+                } else {
+                        None
+                };
+
+                Some(Self {
+                        initial: *offsets.first()?,
+                        transitions,
+                        rule,
+                })
+        }
+
+        fn offset_at(&self, ts: i64) -> i32 {
+                let count = self.transitions.partition_point(|(at, _)| *at <= ts);
+                if count == self.transitions.len()
+                        && let Some(rule) = &self.rule
+                {
+                        return rule.offset_at(ts);
+                }
+
+                match count {
+                        0 => self.initial,
+                        n => self.transitions[n - 1].1,
+                }
+        }
+}
+
+struct Header {
+        version:  u8,
+        isutcnt:  usize,
+        isstdcnt: usize,
+        leapcnt:  usize,
+        timecnt:  usize,
+        typecnt:  usize,
+        charcnt:  usize,
+}
+
+impl Header {
+        /// Length of the data block that follows the header.
+        fn data_len(&self, time_size: usize) -> usize {
+                self.timecnt * (time_size + 1)
+                        + self.typecnt * 6
+                        + self.charcnt
+                        + self.leapcnt * (time_size + 4)
+                        + self.isstdcnt
+                        + self.isutcnt
+        }
+}
+
+struct Reader<'a> {
+        data: &'a [u8],
+        pos:  usize,
+}
+
+impl<'a> Reader<'a> {
+        fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+                let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+                self.pos += len;
+                Some(bytes)
+        }
+
+        fn skip(&mut self, len: usize) -> Option<()> {
+                self.take(len).map(|_| ())
+        }
+
+        fn rest(&self) -> &'a [u8] {
+                &self.data[self.pos..]
+        }
+
+        /// Reads a big-endian signed integer of 4 or 8 bytes.
+        fn int(&mut self, size: usize) -> Option<i64> {
+                let bytes = self.take(size)?;
+                Some(match size {
+                        4 => i32::from_be_bytes(bytes.try_into().ok()?) as i64,
+                        _ => i64::from_be_bytes(bytes.try_into().ok()?),
+                })
+        }
+
+        fn count(&mut self) -> Option<usize> {
+                let bytes = self.take(4)?;
+                Some(u32::from_be_bytes(bytes.try_into().ok()?) as usize)
+        }
+
+        fn header(&mut self) -> Option<Header> {
+                if self.take(4)? != b"TZif" {
+                        return None;
+                }
+                let version = self.take(1)?[0];
+                self.skip(15)?;
+
+                Some(Header {
+                        version,
+                        isutcnt: self.count()?,
+                        isstdcnt: self.count()?,
+                        leapcnt: self.count()?,
+                        timecnt: self.count()?,
+                        typecnt: self.count()?,
+                        charcnt: self.count()?,
+                })
+        }
+}
+
+/// A POSIX TZ string such as `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Debug)]
+struct Rule {
+        std: i32,
+        dst: Option<(i32, Transition, Transition)>,
+}
+
+/// The day and local time at which daylight saving time starts or ends.
+#[derive(Debug)]
+struct Transition {
+        day:  TransitionDay,
+        time: i64,
+}
+
+#[derive(Debug)]
+enum TransitionDay {
+        /// `Jn`: day 1 to 365, where February 29 is never counted.
+        Julian(i64),
+        /// `n`: day 0 to 365, counting February 29 in leap years.
+        Zero(i64),
+        /// `Mm.w.d`: day `d` (0 is Sunday) of week `w` of month `m`, where
+        /// week 5 is the last one.
+        Month(u8, u8, u8),
+}
+
+impl Rule {
+        fn from_footer(footer: &[u8]) -> Option<Self> {
+                let footer = footer.strip_prefix(b"\n")?;
+                let end = footer.iter().position(|&b| b == b'\n')?;
+                Self::parse(std::str::from_utf8(&footer[..end]).ok()?)
+        }
+
+        fn parse(s: &str) -> Option<Self> {
+                let mut parser = RuleParser { rest: s };
+
+                parser.name()?;
+                let std = -parser.offset()?;
+                if parser.rest.is_empty() {
+                        return Some(Self { std, dst: None });
+                }
+
+                parser.name()?;
+                let dst = if parser.rest.starts_with(',') {
+                        std + 3600
+                } else {
+                        -parser.offset()?
+                };
+
+                let (start, end) = if parser.rest.is_empty() {
+                        // The rules of the United States, as assumed by POSIX
+                        (
+                                Transition {
+                                        day:  TransitionDay::Month(3, 2, 0),
+                                        time: 7200,
+                                },
+                                Transition {
+                                        day:  TransitionDay::Month(11, 1, 0),
+                                        time: 7200,
+                                },
+                        )
+                } else {
+                        parser.expect(',')?;
+                        let start = parser.transition()?;
+                        parser.expect(',')?;
+                        (start, parser.transition()?)
+                };
+
+                parser.rest.is_empty().then_some(Self {
+                        std,
+                        dst: Some((dst, start, end)),
+                })
+        }
+
+        fn offset_at(&self, ts: i64) -> i32 {
+                let Some((dst, start, end)) = &self.dst else {
+                        return self.std;
+                };
+
+                let year = DateTime::new(ts as u64, self.std).year;
+                // Start times are given in standard time, end times in DST
+                let starts = start.local_time(year) - self.std as i64;
+                let ends = end.local_time(year) - *dst as i64;
+
+                let is_dst = if starts < ends {
+                        (starts..ends).contains(&ts)
+                } else {
+                        // Southern hemisphere: DST spans the new year
+                        !(ends..starts).contains(&ts)
+                };
+                if is_dst { *dst } else { self.std }
+        }
+}
+
+impl Transition {
+        /// The transition in `year`, as seconds since the epoch in local time.
+        fn local_time(&self, year: i32) -> i64 {
+                let jan_first = DateTime::from_parts(year, 1, 1, 0, 0, 0)
+                        .map_or(0, |dt| dt.timestamp(0) / 86_400);
+                let is_leap = days_in_month(year, 2) == 29;
+
+                let day = match self.day {
+                        TransitionDay::Julian(n) => {
+                                jan_first + n - 1 + i64::from(is_leap && n >= 60)
+                        },
+                        TransitionDay::Zero(n) => jan_first + n,
+                        TransitionDay::Month(month, week, weekday) => {
+                                let first_date = DateTime::from_parts(year, month, 1, 0, 0, 0);
+                                let first = first_date
+                                        .as_ref()
+                                        .map_or(0, |dt| dt.timestamp(0) / 86_400);
+                                // POSIX rules number weekdays from 0 for Sunday
+                                let first_weekday =
+                                        first_date.map_or(0, |dt| (dt.weekday() + 1).rem_euclid(7));
+                                let mut day = (weekday as i64 - first_weekday).rem_euclid(7)
+                                        + (week as i64 - 1) * 7;
+                                while day >= days_in_month(year, month) as i64 {
+                                        day -= 7;
+                                }
+                                first + day
+                        },
+                };
+
+                day * 86_400 + self.time
+        }
+}
+
+struct RuleParser<'a> {
+        rest: &'a str,
+}
+
+impl RuleParser<'_> {
+        fn expect(&mut self, c: char) -> Option<()> {
+                self.rest = self.rest.strip_prefix(c)?;
+                Some(())
+        }
+
+        /// Skips a zone abbreviation such as `CET` or `<-03>`.
+        fn name(&mut self) -> Option<()> {
+                let len = if let Some(quoted) = self.rest.strip_prefix('<') {
+                        quoted.find('>')? + 2
+                } else {
+                        self.rest
+                                .find(|c: char| !c.is_ascii_alphabetic())
+                                .unwrap_or(self.rest.len())
+                };
+                if len < 3 {
+                        return None;
+                }
+                self.rest = &self.rest[len..];
+                Some(())
+        }
+
+        fn number(&mut self) -> Option<i64> {
+                let len = self
+                        .rest
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(self.rest.len());
+                let n = self.rest[..len].parse().ok()?;
+                self.rest = &self.rest[len..];
+                Some(n)
+        }
+
+        /// Parses `[+-]hh[:mm[:ss]]` into seconds.
+        fn time(&mut self) -> Option<i64> {
+                let sign = if self.rest.starts_with('-') { -1 } else { 1 };
+                self.rest = self.rest.trim_start_matches(['+', '-']);
+
+                let mut seconds = self.number()? * 3600;
+                for unit in [60, 1] {
+                        if self.expect(':').is_none() {
+                                break;
+                        }
+                        seconds += self.number()? * unit;
+                }
+                Some(sign * seconds)
+        }
+
+        fn offset(&mut self) -> Option<i32> {
+                i32::try_from(self.time()?).ok()
+        }
+
+        fn transition(&mut self) -> Option<Transition> {
+                let day = if self.expect('J').is_some() {
+                        TransitionDay::Julian(self.number()?)
+                } else if self.expect('M').is_some() {
+                        let month = self.number()?;
+                        self.expect('.')?;
+                        let week = self.number()?;
+                        self.expect('.')?;
+                        let weekday = self.number()?;
+                        if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                                return None;
+                        }
+                        TransitionDay::Month(month as u8, week as u8, weekday as u8)
+                } else {
+                        TransitionDay::Zero(self.number()?)
+                };
+
+                let time = if self.expect('/').is_some() {
+                        self.time()?
+                } else {
+                        7200
+                };
+                Some(Transition { day, time })
+        }
+}
+
+#[cfg(test)]
+mod tests {
+        use super::*;
+
+        /// The offset of `rule` at `local`, a local time in `offset`.
+        fn at(rule: &Rule, local: &str, offset: i32) -> Option<i32> {
+                let ts = DateTime::parse(local)?.timestamp(offset);
+                Some(rule.offset_at(ts))
+        }
+
+        #[test]
+        fn parses_rules_without_dst() {
+                let rule = Rule::parse("<-03>3");
+                assert_eq!(rule.map(|r| r.offset_at(0)), Some(-3 * 3600));
+                assert!(Rule::parse("").is_none());
+                assert!(Rule::parse("CET-1CEST,M3.5.0").is_none());
+        }
+
+        #[test]
+        fn follows_northern_dst_rules() -> Result<(), String> {
+                let rule = Rule::parse("CET-1CEST,M3.5.0,M10.5.0/3").ok_or("invalid rule")?;
+                assert_eq!(at(&rule, "2026-01-15T12:00", 3600), Some(3600));
+                assert_eq!(at(&rule, "2026-07-01T12:00", 7200), Some(7200));
+                // Clocks go forward at 02:00 on the last Sunday of March
+                assert_eq!(at(&rule, "2026-03-29T01:59:59", 3600), Some(3600));
+                assert_eq!(at(&rule, "2026-03-29T03:00", 7200), Some(7200));
+                // And back at 03:00 on the last Sunday of October
+                assert_eq!(at(&rule, "2026-10-25T02:59:59", 7200), Some(7200));
+                assert_eq!(at(&rule, "2026-10-25T02:00", 3600), Some(3600));
+                Ok(())
+        }
+
+        #[test]
+        fn follows_southern_dst_rules() -> Result<(), String> {
+                let rule = Rule::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").ok_or("invalid rule")?;
+                assert_eq!(at(&rule, "2026-01-15T12:00", 39600), Some(39600));
+                assert_eq!(at(&rule, "2026-07-01T12:00", 36000), Some(36000));
+                assert_eq!(at(&rule, "2026-12-31T23:00", 39600), Some(39600));
+                Ok(())
+        }
+
+        #[test]
+        fn resolves_local_times_around_transitions() -> Result<(), String> {
+                let rule = Rule::parse("CET-1CEST,M3.5.0,M10.5.0/3").ok_or("invalid rule")?;
+                let tz = TimeZone::Zone(Zone {
+                        initial:     3600,
+                        transitions: Vec::new(),
+                        rule:        Some(rule),
+                });
+                let local = |s: &str| DateTime::parse(s).map(|dt| tz.timestamp(&dt));
+                let utc = |s: &str| DateTime::parse(s).map(|dt| dt.timestamp(0));
+
+                assert_eq!(local("2026-07-01T12:00"), utc("2026-07-01T10:00"));
+                // 02:30 happens twice when clocks go back, the first one wins
+                assert_eq!(local("2026-10-25T02:30"), utc("2026-10-25T00:30"));
+                // 02:30 doesn't happen when clocks go forward
+                assert_eq!(local("2026-03-29T02:30"), utc("2026-03-29T01:30"));
+                Ok(())
+        }
+
+        #[test]
+        fn reads_tzif_files() -> Result<(), String> {
+                let mut data = Vec::new();
+                // A version 1 file with a single transition from -02:00 to -03:00
+                data.extend(b"TZif\0");
+                data.extend([0; 15]);
+                for count in [0u32, 0, 0, 1, 2, 4] {
+                        data.extend(count.to_be_bytes());
+                }
+                data.extend(1_000_000i32.to_be_bytes());
+                data.push(1);
+                data.extend((-7200i32).to_be_bytes());
+                data.extend([0, 0]);
+                data.extend((-10800i32).to_be_bytes());
+                data.extend([0, 0]);
+                data.extend(b"-02\0");
+
+                let zone = Zone::parse(&data).ok_or("invalid zone")?;
+                assert_eq!(zone.offset_at(999_999), -7200);
+                assert_eq!(zone.offset_at(1_000_000), -10800);
+                assert_eq!(zone.offset_at(i64::MAX), -10800);
+                assert!(Zone::parse(&data[..30]).is_none());
+                Ok(())
+        }
+}
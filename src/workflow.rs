@@ -0,0 +1,118 @@
+use std::fmt::Display;
+
+/// The states a task goes through, as configured with a line such as
+/// `workflow = todo -> doing -> review -> done | cancelled`.
+///
+/// States are listed in steps separated by `->`, and a step may offer
+/// alternatives separated by `|`. The first state is the one new tasks start
+/// in, and the states of the last step are the closed ones.
+pub struct Workflow {
+        steps: Vec<Vec<String>>,
+}
+
+/// `OPEN -> CLOSED`, for stores that don't configure a workflow.
+impl Default for Workflow {
+        fn default() -> Self {
+                Self {
+                        steps: vec![vec!["OPEN".to_string()], vec!["CLOSED".to_string()]],
+                }
+        }
+}
+
+/// Writes the workflow as it is configured, such as `todo -> doing -> done`.
+impl Display for Workflow {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let steps: Vec<String> =
+                        self.steps.iter().map(|states| states.join(" | ")).collect();
+                write!(f, "{}", steps.join(" -> "))
+        }
+}
+
+impl Workflow {
+        pub fn parse(s: &str) -> Result<Self, String> {
+                let mut steps: Vec<Vec<String>> = Vec::new();
+
+                for step in s.split("->") {
+                        let mut states = Vec::new();
+                        for state in step.split('|').map(str::trim) {
+                                if state.is_empty() || state.contains(char::is_whitespace) {
+                                        return Err(format!(
+                                                "expected single-word states separated by '->' or '|', found '{}'",
+                                                step.trim()
+                                        ));
+                                }
+                                let is_duplicate = steps
+                                        .iter()
+                                        .flatten()
+                                        .chain(&states)
+                                        .any(|known| known.eq_ignore_ascii_case(state));
+                                if is_duplicate {
+                                        return Err(format!("state '{}' is listed twice", state));
+                                }
+                                states.push(state.to_string());
+                        }
+                        steps.push(states);
+                }
+
+                if steps.len() < 2 {
+                        return Err("expected at least two steps separated by '->'".to_string());
+                }
+                Ok(Self { steps })
+        }
+
+        /// The state new and reopened tasks are in.
+        pub fn initial(&self) -> &str {
+                &self.steps[0][0]
+        }
+
+        /// The state tasks are closed to by `done`.
+        pub fn closed(&self) -> &str {
+                &self.steps[self.steps.len() - 1][0]
+        }
+
+        /// Returns the state named `name`, ignoring case, as it is spelled in
+        /// the workflow.
+        pub fn find(&self, name: &str) -> Option<&str> {
+                self.states().find(|s| s.eq_ignore_ascii_case(name))
+        }
+
+        pub fn states(&self) -> impl Iterator<Item = &str> {
+                self.steps.iter().flatten().map(String::as_str)
+        }
+
+        pub fn is_initial(&self, state: &str) -> bool {
+                self.initial().eq_ignore_ascii_case(state)
+        }
+
+        /// Whether `state` is one of the states of the last step.
+        pub fn is_closed(&self, state: &str) -> bool {
+                self.step_of(state) == Some(self.steps.len() - 1)
+        }
+
+        /// The states a task in `state` may be moved to: those of the next step,
+        /// going forward, or of any earlier step, going back.
+        pub fn next_states(&self, state: &str) -> Vec<&str> {
+                let Some(step) = self.step_of(state) else {
+                        return Vec::new();
+                };
+
+                self.steps
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| *i < step || *i == step + 1)
+                        .flat_map(|(_, states)| states.iter().map(String::as_str))
+                        .collect()
+        }
+
+        pub fn can_move(&self, from: &str, to: &str) -> bool {
+                self.next_states(from)
+                        .iter()
+                        .any(|s| s.eq_ignore_ascii_case(to))
+        }
+
+        fn step_of(&self, state: &str) -> Option<usize> {
+                self.steps
+                        .iter()
+                        .position(|states| states.iter().any(|s| s.eq_ignore_ascii_case(state)))
+        }
+}
@@ -1,19 +1,53 @@
 use std::error::Error;
-use std::fs;
+use std::path::{
+        Path,
+        PathBuf,
+};
+use std::{
+        fs,
+        process,
+};
 
-use assert_cmd::cargo_bin_cmd;
 use predicates::prelude::*;
 
+/// A `tafsk` process run in `dir`, kept apart from the environment of whoever
+/// runs the tests: it has no user configuration, global store or editor but
+/// those the test sets.
+fn tafsk_process(dir: &Path) -> process::Command {
+        let mut cmd = process::Command::new(assert_cmd::cargo::cargo_bin!("tafsk"));
+        cmd.current_dir(dir)
+                .env("XDG_CONFIG_HOME", dir)
+                .env_remove("TAFSK_STORE_DIR")
+                .env_remove("EDITOR");
+        cmd
+}
+
+/// A `tafsk` command run in `dir`, as set up by [`tafsk_process`].
+fn tafsk(dir: &Path) -> assert_cmd::Command {
+        assert_cmd::Command::from_std(tafsk_process(dir))
+}
+
+/// Returns the `field`, such as `id` or `huid`, of the task titled `title` in
+/// the store at `dir`, closed or not.
+fn field_of(dir: &Path, field: &str, title: &str) -> Result<String, Box<dyn Error>> {
+        let output = tafsk(dir)
+                .args(["-c", "-f", &format!("{{{}}} {{title}}", field)])
+                .output()?;
+        String::from_utf8(output.stdout)?
+                .lines()
+                .find_map(|line| line.strip_suffix(&format!(" {}", title)))
+                .map(str::to_string)
+                .ok_or_else(|| format!("task '{}' is not listed", title).into())
+}
+
 #[test]
 fn should_fail_when_no_task_store_is_found() -> Result<(), Box<dyn std::error::Error>> {
         let tmp = tempfile::tempdir()?;
 
-        let mut cmd = cargo_bin_cmd!("tafsk");
-
-        cmd.current_dir(tmp.path()) // Sets the current directory to /tmp/.tmpXXXXXX
-                .env_remove("TAFSK_STORE_DIR")
-                .arg("add")
-                .arg("Should Fail");
+        // Neither a local store, nor a global one in the environment or the
+        // user configuration
+        let mut cmd = tafsk(tmp.path());
+        cmd.arg("add").arg("Should Fail");
 
         cmd.assert()
                 .failure()
@@ -28,9 +62,8 @@ fn should_create_task_in_global_store_when_env_var_is_set() -> Result<(), Box<dy
         let global_store = tmp.path().join("global_tasks");
         let _ = fs::create_dir(&global_store);
 
-        let mut cmd = cargo_bin_cmd!("tafsk");
-        cmd.current_dir(tmp.path())
-                .env("TAFSK_STORE_DIR", &global_store)
+        let mut cmd = tafsk(tmp.path());
+        cmd.env("TAFSK_STORE_DIR", &global_store)
                 .arg("add")
                 .arg("Global Task");
 
@@ -49,10 +82,9 @@ fn should_create_local_store_when_init_is_run() -> Result<(), Box<dyn Error>> {
         let mock_global_store = tmp.path().join("mock_global_store");
         fs::create_dir(&mock_global_store)?; // It must exist for the env var to be valid in `Store::new`
 
-        let mut cmd = cargo_bin_cmd!("tafsk");
+        let mut cmd = tafsk(tmp.path());
 
-        cmd.current_dir(tmp.path())
-                .env("TAFSK_STORE_DIR", &mock_global_store) // Set global env var
+        cmd.env("TAFSK_STORE_DIR", &mock_global_store) // Set global env var
                 .arg("init");
 
         cmd.assert().success();
@@ -90,15 +122,11 @@ fn should_use_global_store_explicitly_when_local_exists() -> Result<(), Box<dyn
         let global_store = tmp.path().join("global_store");
         fs::create_dir(&global_store)?;
 
-        let mut cmd_init = cargo_bin_cmd!("tafsk");
-        cmd_init.current_dir(tmp.path())
-                .arg("init")
-                .assert()
-                .success();
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.arg("init").assert().success();
 
-        let mut cmd_add = cargo_bin_cmd!("tafsk");
-        cmd_add.current_dir(tmp.path())
-                .env("TAFSK_STORE_DIR", &global_store)
+        let mut cmd_add = tafsk(tmp.path());
+        cmd_add.env("TAFSK_STORE_DIR", &global_store)
                 .arg("add")
                 .arg("--global")
                 .arg("Global Task")
@@ -110,13 +138,15 @@ fn should_use_global_store_explicitly_when_local_exists() -> Result<(), Box<dyn
         assert!(has_global_task_created);
 
         let local_store = tmp.path().join("tasks");
-        let local_entries = fs::read_dir(&local_store)?;
+        // Nothing but the configuration and the lock of the store
+        let local_entries = fs::read_dir(&local_store)?
+                .filter_map(Result::ok)
+                .filter(|e| e.file_name() != ".lock");
         let is_local_store_empty = local_entries.count() == 1;
         assert!(is_local_store_empty);
 
-        let mut cmd_list_global = cargo_bin_cmd!("tafsk");
+        let mut cmd_list_global = tafsk(tmp.path());
         cmd_list_global
-                .current_dir(tmp.path())
                 .env("TAFSK_STORE_DIR", &global_store)
                 .arg("list")
                 .arg("--global")
@@ -124,9 +154,8 @@ fn should_use_global_store_explicitly_when_local_exists() -> Result<(), Box<dyn
                 .success()
                 .stdout(predicate::str::contains("Global Task"));
 
-        let mut cmd_list_local = cargo_bin_cmd!("tafsk");
+        let mut cmd_list_local = tafsk(tmp.path());
         cmd_list_local
-                .current_dir(tmp.path())
                 .env("TAFSK_STORE_DIR", &global_store)
                 .arg("list")
                 .assert()
@@ -135,3 +164,1688 @@ fn should_use_global_store_explicitly_when_local_exists() -> Result<(), Box<dyn
 
         Ok(())
 }
+
+/// Writes an open task at `folder`, with `tags` as written between the
+/// brackets of its `tags:` list, such as `work, infra`.
+fn write_task(store: &Path, folder: &str, title: &str, tags: &str) -> Result<(), Box<dyn Error>> {
+        let dir = store.join(folder);
+        fs::create_dir_all(&dir)?;
+        fs::write(
+                dir.join("TASK.md"),
+                format!(
+                        "---\nstatus: OPEN\npriority: 10\ntags: [{}]\n---\n\n# {}\n",
+                        tags, title
+                ),
+        )?;
+        Ok(())
+}
+
+#[test]
+fn should_close_task_by_huid_prefix() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010809.f3fe84e5", "First", "")?;
+        write_task(&store, "20251219-020000.0a1b2c3d", "Second", "")?;
+
+        let mut cmd_done_hash = tafsk(tmp.path());
+        cmd_done_hash
+                .arg("done")
+                .arg("f3fe84")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "Completed task [f3fe84e5] 'First'",
+                ));
+
+        let content = fs::read_to_string(store.join("20251219-010809.f3fe84e5").join("TASK.md"))?;
+        assert!(content.contains("status: CLOSED"));
+
+        let mut cmd_done_huid = tafsk(tmp.path());
+        cmd_done_huid
+                .arg("done")
+                .arg("20251219-02")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("'Second'"));
+
+        Ok(())
+}
+
+#[test]
+fn should_reject_ambiguous_id_prefix() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010809.f3fe84e5", "First", "")?;
+        write_task(&store, "20251219-020000.0a1b2c3d", "Second", "")?;
+
+        let mut cmd = tafsk(tmp.path());
+        cmd.arg("done")
+                .arg("20251219")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("ambiguous"))
+                .stderr(predicate::str::contains("20251219-010809.f3fe84e5"))
+                .stderr(predicate::str::contains("20251219-020000.0a1b2c3d"));
+
+        Ok(())
+}
+
+#[test]
+fn should_not_resolve_ids_as_positions() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010809.aaaaaaaa", "First", "")?;
+        write_task(&store, "20251219-020000.1bbbbbbb", "Second", "")?;
+        write_task(&store, "20251219-030000.cccccccc", "Third", "")?;
+
+        // A number is only ever a prefix: of the hash `1bbbbbbb` here
+        let mut cmd_one = tafsk(tmp.path());
+        cmd_one.arg("done")
+                .arg("1")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "Completed task [1bbbbbbb] 'Second'",
+                ));
+
+        // and of every HUID here, rather than the second task
+        let mut cmd_two = tafsk(tmp.path());
+        cmd_two.arg("done")
+                .arg("2")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("ID '2' is ambiguous"));
+
+        let mut cmd_four = tafsk(tmp.path());
+        cmd_four.arg("done")
+                .arg("4")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("No task matches ID '4'"));
+
+        for folder in ["20251219-010809.aaaaaaaa", "20251219-030000.cccccccc"] {
+                let content = fs::read_to_string(store.join(folder).join("TASK.md"))?;
+                assert!(content.contains("status: OPEN"), "{}", folder);
+        }
+
+        Ok(())
+}
+
+fn write_editor_script(dir: &Path, body: &str) -> Result<PathBuf, Box<dyn Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = dir.join("editor.sh");
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body))?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        Ok(script)
+}
+
+#[test]
+fn should_keep_valid_edits() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010809.f3fe84e5", "First", "")?;
+        let editor = write_editor_script(tmp.path(), "echo 'Some notes' >> \"$1\"")?;
+
+        let mut cmd = tafsk(tmp.path());
+        cmd.env("EDITOR", &editor)
+                .arg("edit")
+                .arg("f3fe")
+                .assert()
+                .success();
+
+        let content = fs::read_to_string(store.join("20251219-010809.f3fe84e5").join("TASK.md"))?;
+        assert!(content.ends_with("Some notes\n"));
+
+        Ok(())
+}
+
+#[test]
+fn should_restore_task_when_edit_breaks_it() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010809.f3fe84e5", "First", "")?;
+        let path = store.join("20251219-010809.f3fe84e5").join("TASK.md");
+        let original = fs::read_to_string(&path)?;
+        let editor = write_editor_script(tmp.path(), "echo 'garbage' > \"$1\"")?;
+
+        let mut cmd = tafsk(tmp.path());
+        cmd.env("EDITOR", &editor)
+                .arg("edit")
+                .arg("f3fe")
+                .write_stdin("r\n")
+                .assert()
+                .success()
+                .stderr(predicate::str::contains("is not a valid task"))
+                .stderr(predicate::str::contains("Restored previous content"));
+
+        assert_eq!(fs::read_to_string(&path)?, original);
+
+        Ok(())
+}
+
+#[test]
+fn should_modify_tasks_preserving_body_and_unknown_keys() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        let dir = store.join("20251219-010809.f3fe84e5");
+        fs::create_dir_all(&dir)?;
+        fs::write(
+                dir.join("TASK.md"),
+                "---\nstatus: OPEN\npriority: 10\nowner: ana\ntags: [perf, ops]\n---\n\n# Old title\n\nKeep me.\n",
+        )?;
+        write_task(&store, "20251219-020000.0a1b2c3d", "Second", "")?;
+
+        let mut cmd = tafsk(tmp.path());
+        cmd.arg("modify")
+                .arg("f3fe")
+                .arg("0a1b")
+                .arg("-p")
+                .arg("50")
+                .arg("--")
+                .arg("-perf")
+                .arg("+infra")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("priority: 10 -> 50"))
+                .stdout(predicate::str::contains(
+                        "tags: [perf, ops] -> [ops, infra]",
+                ))
+                .stdout(predicate::str::contains("tags: [] -> [infra]"));
+
+        let content = fs::read_to_string(dir.join("TASK.md"))?;
+        assert_eq!(
+                content,
+                "---\nstatus: OPEN\npriority: 50\nowner: ana\ntags: [ops, infra]\n---\n\n# Old title\n\nKeep me.\n"
+        );
+
+        let mut cmd_title = tafsk(tmp.path());
+        cmd_title
+                .arg("modify")
+                .arg("f3fe")
+                .arg("--title")
+                .arg("New title")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "title: 'Old title' -> 'New title'",
+                ));
+
+        let content_after_title = fs::read_to_string(dir.join("TASK.md"))?;
+        assert!(content_after_title.contains("\n# New title\n\nKeep me.\n"));
+
+        Ok(())
+}
+
+#[test]
+fn should_reopen_closed_task() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010809.f3fe84e5", "First", "")?;
+        let path = store.join("20251219-010809.f3fe84e5").join("TASK.md");
+        let original = fs::read_to_string(&path)?;
+
+        let mut cmd_reopen_open = tafsk(tmp.path());
+        cmd_reopen_open
+                .arg("reopen")
+                .arg("f3fe")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("is already OPEN"));
+
+        let mut cmd_done = tafsk(tmp.path());
+        cmd_done.arg("done").arg("f3fe").assert().success();
+
+        let mut cmd_reopen = tafsk(tmp.path());
+        cmd_reopen
+                .arg("reopen")
+                .arg("f3fe")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "Reopened task [f3fe84e5] 'First'.",
+                ));
+
+        assert_eq!(fs::read_to_string(&path)?, original);
+
+        Ok(())
+}
+
+#[test]
+fn should_filter_list_by_tags() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(
+                &store,
+                "20251219-010000.00000001",
+                "Deploy app",
+                "work, infra",
+        )?;
+        write_task(
+                &store,
+                "20251219-020000.00000002",
+                "Fix pipeline",
+                "work, blocked",
+        )?;
+        write_task(&store, "20251219-030000.00000003", "Water plants", "home")?;
+
+        let mut cmd_with = tafsk(tmp.path());
+        cmd_with.arg("list")
+                .arg("+work")
+                .arg("-blocked")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Deploy app"))
+                .stdout(predicate::str::contains("Fix pipeline").not())
+                .stdout(predicate::str::contains("Water plants").not());
+
+        let mut cmd_any = tafsk(tmp.path());
+        cmd_any.arg("-t")
+                .arg("infra,home")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Deploy app"))
+                .stdout(predicate::str::contains("Fix pipeline").not())
+                .stdout(predicate::str::contains("Water plants"));
+
+        let mut cmd_implicit = tafsk(tmp.path());
+        cmd_implicit
+                .arg("-work")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Water plants"))
+                .stdout(predicate::str::contains("Deploy app").not());
+
+        Ok(())
+}
+
+#[test]
+fn should_parse_attached_values_as_flags_rather_than_tags() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010000.00000001", "Write docs", "doc")?;
+        write_task(&store, "20251219-020000.00000002", "Fix tests", "tdoc")?;
+        write_task(&store, "20251219-030000.00000003", "Water plants", "")?;
+
+        tafsk(tmp.path())
+                .args(["list", "-fjson"])
+                .assert()
+                .success()
+                .stdout(predicate::str::starts_with("[{\"version\""));
+
+        tafsk(tmp.path())
+                .args(["list", "-tdoc"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Write docs"))
+                .stdout(predicate::str::contains("Fix tests").not())
+                .stdout(predicate::str::contains("Water plants").not());
+
+        tafsk(tmp.path())
+                .args(["list", "--", "-tdoc"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Write docs"))
+                .stdout(predicate::str::contains("Fix tests").not())
+                .stdout(predicate::str::contains("Water plants"));
+
+        tafsk(tmp.path())
+                .args(["modify", "00000002", "-ptest"])
+                .assert()
+                .failure();
+        tafsk(tmp.path())
+                .args(["modify", "00000002", "--", "-tdoc"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("tags: [tdoc] -> []"));
+
+        Ok(())
+}
+
+#[test]
+fn should_select_tasks_with_query() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010000.00000001", "Deploy app", "work")?;
+        write_task(&store, "20251220-020000.00000002", "Deploy docs", "ops")?;
+        write_task(&store, "20251221-030000.00000003", "Water plants", "home")?;
+
+        let mut cmd_list = tafsk(tmp.path());
+        cmd_list.arg("list")
+                .arg("-q")
+                .arg(r#"(tag:work or tag:ops) and title~"deploy" and created>=2025-12-20"#)
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Deploy docs"))
+                .stdout(predicate::str::contains("Deploy app").not())
+                .stdout(predicate::str::contains("Water plants").not());
+
+        let mut cmd_done = tafsk(tmp.path());
+        cmd_done.arg("done")
+                .arg("--query")
+                .arg("title~deploy")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "Completed task [00000001] 'Deploy app'.",
+                ))
+                .stdout(predicate::str::contains(
+                        "Completed task [00000002] 'Deploy docs'.",
+                ));
+
+        let mut cmd_closed = tafsk(tmp.path());
+        cmd_closed
+                .arg("-q")
+                .arg("status:closed")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Deploy app"))
+                .stdout(predicate::str::contains("Water plants").not());
+
+        Ok(())
+}
+
+#[test]
+fn should_point_at_query_parse_errors() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        fs::create_dir(tmp.path().join("tasks"))?;
+
+        let mut cmd = tafsk(tmp.path());
+        cmd.arg("list")
+                .arg("-q")
+                .arg("priority>=50 and colour:red")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("unknown field 'colour'"))
+                .stderr(predicate::str::contains(
+                        "    priority>=50 and colour:red\n                     ^",
+                ));
+
+        // Spaces around the operator are skipped before pointing
+        let mut cmd_spaced = tafsk(tmp.path());
+        cmd_spaced
+                .args(["list", "-q", "priority>= and x"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                        "    priority>= and x\n               ^",
+                ));
+
+        let mut cmd_operator = tafsk(tmp.path());
+        cmd_operator
+                .args(["list", "-q", "title  <  x"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("    title  <  x\n           ^"));
+
+        Ok(())
+}
+
+#[test]
+fn should_print_tasks_as_json() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        fs::create_dir_all(&store)?;
+        fs::write(store.join(".config"), "-03:00")?;
+        let dir = store.join("20251219-010809.f3fe84e5");
+        fs::create_dir_all(&dir)?;
+        fs::write(
+                dir.join("TASK.md"),
+                "---\nstatus: OPEN\npriority: 42\ntags: [work]\n---\n\n# Say \"hi\"\n\nLine one\nLine two\n",
+        )?;
+
+        let mut cmd_jsonl = tafsk(tmp.path());
+        let output = cmd_jsonl
+                .arg("list")
+                .arg("--format")
+                .arg("jsonl")
+                .output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        let expected = format!(
+                "{{\"version\":2,\"id\":\"f3fe84e5\",\"huid\":\"20251219-010809.f3fe84e5\",\"path\":\"{}\",\"line\":7,\"status\":\"OPEN\",\"priority\":42,\"tags\":[\"work\"],\"title\":\"Say \\\"hi\\\"\",\"created\":\"2025-12-19T01:08:09-03:00\",\"closed\":null,\"due\":null,\"parent\":null,\"depends\":[],\"body\":\"Line one\\nLine two\"}}\n",
+                dir.join("TASK.md").display()
+        );
+        assert_eq!(stdout, expected);
+
+        let mut cmd_json = tafsk(tmp.path());
+        cmd_json.arg("-f")
+                .arg("json")
+                .arg("-work")
+                .assert()
+                .success()
+                .stdout("[]\n");
+
+        Ok(())
+}
+
+#[test]
+fn should_render_list_with_templates() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(
+                &store,
+                "20251219-010000.00000001",
+                "Deploy the application",
+                "work",
+        )?;
+
+        let mut cmd_inline = tafsk(tmp.path());
+        cmd_inline
+                .arg("list")
+                .arg("--format")
+                .arg("{id}|{priority:>4}|{title:<8.6}|{tags}|{{}}")
+                .assert()
+                .success()
+                .stdout("00000001|  10|Deploy  |work|{}\n");
+
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.arg("init").arg("+02:00").assert().success();
+        let config = store.join(".config");
+        let content = fs::read_to_string(&config)? + "\nformat.fzf = {id}\t{title}\n";
+        fs::write(&config, content)?;
+
+        let mut cmd_named = tafsk(tmp.path());
+        cmd_named
+                .arg("-f")
+                .arg("fzf")
+                .assert()
+                .success()
+                .stdout("00000001\tDeploy the application\n");
+
+        // Re-initializing must not drop the shared templates
+        let mut cmd_reinit = tafsk(tmp.path());
+        cmd_reinit.arg("init").arg("-03:00").assert().success();
+        assert!(fs::read_to_string(&config)?.contains("format.fzf = {id}\t{title}"));
+
+        let mut cmd_unknown = tafsk(tmp.path());
+        cmd_unknown
+                .arg("-f")
+                .arg("{owner}")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("unknown field 'owner'"));
+
+        Ok(())
+}
+
+#[test]
+fn should_point_quickfix_lines_at_the_actual_title() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        let dir = store.join("20251219-010809.f3fe84e5");
+        fs::create_dir_all(&dir)?;
+        fs::write(
+                dir.join("TASK.md"),
+                "---\nowner: ana\nstatus: OPEN\npriority: 10\ntags: []\n---\n\n\n# Title\n",
+        )?;
+        let path = dir.join("TASK.md");
+
+        let mut cmd_title = tafsk(tmp.path());
+        cmd_title
+                .assert()
+                .success()
+                .stdout(predicate::str::starts_with(format!(
+                        "{}:9:f3fe84e5:",
+                        path.display()
+                )));
+
+        let mut cmd_status = tafsk(tmp.path());
+        cmd_status
+                .arg("list")
+                .arg("--line")
+                .arg("status")
+                .assert()
+                .success()
+                .stdout(predicate::str::starts_with(format!(
+                        "{}:3:f3fe84e5:",
+                        path.display()
+                )));
+
+        tafsk(tmp.path())
+                .args(["list", "--line", "owner"])
+                .assert()
+                .success()
+                .stdout(predicate::str::starts_with(format!(
+                        "{}:2:f3fe84e5:",
+                        path.display()
+                )));
+
+        tafsk(tmp.path())
+                .args(["list", "--line", "due"])
+                .assert()
+                .success()
+                .stdout(predicate::str::starts_with(format!(
+                        "{}:9:f3fe84e5:",
+                        path.display()
+                )));
+
+        tafsk(tmp.path())
+                .args(["list", "--line", "nosuchkey"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                        "Unknown key 'nosuchkey' for --line",
+                ));
+
+        Ok(())
+}
+
+#[test]
+fn should_sort_and_query_by_the_created_key_over_the_huid() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        tafsk(tmp.path())
+                .args(["init", "-03:00"])
+                .assert()
+                .success();
+
+        // Its HUID is older than the time it says it was created at
+        let imported = store.join("20251219-010000.00000001");
+        fs::create_dir_all(&imported)?;
+        fs::write(
+                imported.join("TASK.md"),
+                "---\nstatus: OPEN\npriority: 10\ntags: []\ncreated: 2025-12-25T23:30:00-03:00\n---\n\n# Imported\n",
+        )?;
+        write_task(&store, "20251220-010000.00000002", "Fresh", "")?;
+
+        tafsk(tmp.path())
+                .args(["list", "-f", "{id}", "--sort", "created"])
+                .assert()
+                .success()
+                .stdout("00000002\n00000001\n");
+
+        tafsk(tmp.path())
+                .args(["list", "-f", "{id}", "-q", "created:2025-12-25"])
+                .assert()
+                .success()
+                .stdout("00000001\n");
+
+        tafsk(tmp.path())
+                .args(["list", "-f", "{id}", "-q", "created<2025-12-21"])
+                .assert()
+                .success()
+                .stdout("00000002\n");
+
+        Ok(())
+}
+
+#[test]
+fn should_sort_list_by_several_keys() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20251219-010000.00000001", "Bravo", "work")?;
+        write_task(&store, "20251219-020000.00000002", "Alpha", "home")?;
+        write_task(&store, "20251219-030000.00000003", "Charlie", "")?;
+        let closed = store.join("20251219-040000.00000004");
+        fs::create_dir_all(&closed)?;
+        fs::write(
+                closed.join("TASK.md"),
+                "---\nstatus: CLOSED\npriority: 10\ntags: []\nclosed: 2025-12-20T09:00:00Z\n---\n\n# Delta\n",
+        )?;
+
+        let mut cmd_title = tafsk(tmp.path());
+        cmd_title
+                .args(["list", "-f", "{id}", "--sort", "title"])
+                .assert()
+                .success()
+                .stdout("00000002\n00000001\n00000003\n");
+
+        let mut cmd_created = tafsk(tmp.path());
+        cmd_created
+                .args(["list", "-f", "{id}", "--sort", "priority,-created"])
+                .assert()
+                .success()
+                .stdout("00000003\n00000002\n00000001\n");
+
+        // Untagged tasks come last whatever the direction
+        let mut cmd_tag = tafsk(tmp.path());
+        cmd_tag.args(["list", "-f", "{id}", "--sort", "-tag"])
+                .assert()
+                .success()
+                .stdout("00000001\n00000002\n00000003\n");
+
+        let mut cmd_closed = tafsk(tmp.path());
+        cmd_closed
+                .args(["list", "-c", "-f", "{id}", "--sort", "closed,title"])
+                .assert()
+                .success()
+                .stdout("00000004\n00000002\n00000001\n00000003\n");
+
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.arg("init").assert().success();
+        let config = store.join(".config");
+        fs::write(&config, fs::read_to_string(&config)? + "\nsort = -title\n")?;
+
+        let mut cmd_default = tafsk(tmp.path());
+        cmd_default
+                .args(["-f", "{id}"])
+                .assert()
+                .success()
+                .stdout("00000003\n00000001\n00000002\n");
+
+        let mut cmd_unknown = tafsk(tmp.path());
+        cmd_unknown
+                .args(["list", "--sort", "owner"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("Unknown sort key 'owner'"));
+
+        Ok(())
+}
+
+#[test]
+fn should_filter_tasks_by_due_date() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.args(["init", "-03:00"]).assert().success();
+
+        for (due, title) in [
+                ("2000-01-01", "Long overdue"),
+                ("2999-06-01T17:00", "Far away"),
+        ] {
+                let mut cmd_add = tafsk(tmp.path());
+                cmd_add.args(["add", "--due", due, title])
+                        .assert()
+                        .success();
+        }
+        let mut cmd_add_undated = tafsk(tmp.path());
+        cmd_add_undated.args(["add", "Whenever"]).assert().success();
+
+        let contents: Vec<String> = fs::read_dir(&store)?
+                .filter_map(Result::ok)
+                .filter(|e| e.path().is_dir())
+                .map(|e| fs::read_to_string(e.path().join("TASK.md")))
+                .collect::<Result<_, _>>()?;
+        assert!(contents
+                .iter()
+                .any(|c| c.contains("due: 2999-06-01T17:00:00-03:00\n")));
+
+        let mut cmd_overdue = tafsk(tmp.path());
+        cmd_overdue
+                .args(["list", "--overdue"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "[DUE: 2000-01-01 00:00] Long overdue",
+                ))
+                .stdout(predicate::str::contains("Far away").not())
+                .stdout(predicate::str::contains("Whenever").not());
+
+        let mut cmd_before = tafsk(tmp.path());
+        cmd_before
+                .args([
+                        "list",
+                        "-f",
+                        "{title}",
+                        "--due-before",
+                        "3000-01-01",
+                        "--sort",
+                        "-due",
+                ])
+                .assert()
+                .success()
+                .stdout("Far away\nLong overdue\n");
+
+        let mut cmd_invalid = tafsk(tmp.path());
+        cmd_invalid
+                .args(["add", "--due", "soon", "Broken"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("Invalid due date 'soon'"));
+
+        // Too far to be written with four digits, and then read back
+        let mut cmd_too_far = tafsk(tmp.path());
+        cmd_too_far
+                .args(["add", "--due", "+9999999y", "Never"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("Invalid due date '+9999999y'"));
+
+        let mut cmd_after = tafsk(tmp.path());
+        cmd_after
+                .args(["list", "-f", "{title}", "--sort", "title"])
+                .assert()
+                .success()
+                .stdout("Far away\nLong overdue\nWhenever\n");
+
+        Ok(())
+}
+
+/// Writes a TZif file for a zone that only has the POSIX TZ rule `rule`.
+fn write_zone_file(path: &Path, rule: &str) -> Result<(), Box<dyn Error>> {
+        let mut block = Vec::new();
+        block.extend(b"TZif2");
+        block.extend([0; 15]);
+        // One local time type and its 4-byte designation
+        for count in [0u32, 0, 0, 0, 1, 4] {
+                block.extend(count.to_be_bytes());
+        }
+        block.extend(0i32.to_be_bytes());
+        block.extend([0, 0]);
+        block.extend(b"UTC\0");
+
+        // The 32-bit and 64-bit blocks are the same without transitions
+        let mut data = block.clone();
+        data.extend(&block);
+        data.extend(format!("\n{}\n", rule).as_bytes());
+
+        fs::create_dir_all(path.parent().ok_or("zone file without parent")?)?;
+        fs::write(path, data)?;
+        Ok(())
+}
+
+#[test]
+fn should_follow_dst_of_named_timezones() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        let zoneinfo = tmp.path().join("zoneinfo");
+        write_zone_file(&zoneinfo.join("Test/Berlin"), "CET-1CEST,M3.5.0,M10.5.0/3")?;
+
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.env("TZDIR", &zoneinfo)
+                .args(["init", "Test/Berlin"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("with timezone Test/Berlin"));
+
+        for due in ["2026-01-15T12:00", "2026-07-01T12:00"] {
+                let mut cmd_add = tafsk(tmp.path());
+                cmd_add.env("TZDIR", &zoneinfo)
+                        .args(["add", "--due", due, "Task"])
+                        .assert()
+                        .success();
+        }
+
+        let mut cmd_list = tafsk(tmp.path());
+        cmd_list.env("TZDIR", &zoneinfo)
+                .args(["list", "-f", "{due}", "--sort", "due"])
+                .assert()
+                .success()
+                .stdout("2026-01-15T12:00:00+01:00\n2026-07-01T12:00:00+02:00\n");
+
+        for zone in ["Test/Nowhere", "../zoneinfo/Test/Berlin", "Test"] {
+                let mut cmd_unknown = tafsk(tmp.path());
+                cmd_unknown
+                        .env("TZDIR", &zoneinfo)
+                        .args(["init", zone])
+                        .assert()
+                        .failure()
+                        .stderr(predicate::str::contains("Unknown timezone"));
+        }
+        assert!(fs::read_to_string(store.join(".config"))?.contains("Test/Berlin"));
+
+        Ok(())
+}
+
+#[test]
+fn should_record_creation_and_closing_times() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.args(["init", "+05:30"]).assert().success();
+        let mut cmd_add = tafsk(tmp.path());
+        cmd_add.args(["add", "Ship it"]).assert().success();
+
+        let folder = fs::read_dir(&store)?
+                .filter_map(Result::ok)
+                .find(|e| e.path().is_dir())
+                .ok_or("task was not created")?
+                .path();
+        let id = folder.file_name().ok_or("task has no folder name")?;
+        let task_file = folder.join("TASK.md");
+        let created = fs::read_to_string(&task_file)?;
+        assert!(created
+                .lines()
+                .any(|l| l.starts_with("created: ") && l.ends_with("+05:30")));
+        assert!(!created.contains("closed:"));
+
+        let mut cmd_done = tafsk(tmp.path());
+        cmd_done.arg("done").arg(id).assert().success();
+        let closed = fs::read_to_string(&task_file)?;
+        assert!(closed
+                .lines()
+                .any(|l| l.starts_with("closed: ") && l.ends_with("+05:30")));
+
+        let mut cmd_list = tafsk(tmp.path());
+        cmd_list.args(["list", "-c"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "[STATUS: CLOSED] [CLOSED: just now] Ship it",
+                ));
+
+        let mut cmd_reopen = tafsk(tmp.path());
+        cmd_reopen.arg("reopen").arg(id).assert().success();
+        let reopened = fs::read_to_string(&task_file)?;
+        assert!(!reopened.contains("closed:"));
+        assert_eq!(reopened, created);
+
+        Ok(())
+}
+
+#[test]
+fn should_show_task_ages_and_report_malformed_huids() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        write_task(&store, "20000101-000000.00000001", "Ancient", "")?;
+        write_task(&store, "someday", "Misfiled", "")?;
+
+        let mut cmd_age = tafsk(tmp.path());
+        cmd_age.args(["list", "--age"])
+                .assert()
+                .success()
+                .stdout(predicate::str::is_match(r"\[AGE: \d+y\] Ancient")?)
+                .stdout(predicate::str::contains("Misfiled"))
+                .stderr(predicate::str::contains(
+                        "Warning: Malformed HUID someday: expected YYYYMMDD-HHMMSS.HASH",
+                ));
+
+        let mut cmd_field = tafsk(tmp.path());
+        cmd_field
+                .args(["list", "-f", "{title}:{age}"])
+                .assert()
+                .success()
+                .stdout(predicate::str::is_match(r"^Ancient:\d+y\nMisfiled:\n$")?);
+
+        Ok(())
+}
+
+#[test]
+fn should_move_tasks_through_the_configured_workflow() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.args(["init", "+00:00"]).assert().success();
+        let config = store.join(".config");
+        fs::write(
+                &config,
+                fs::read_to_string(&config)?
+                        + "\nworkflow = todo -> doing -> review -> done | cancelled\n",
+        )?;
+
+        let mut cmd_add = tafsk(tmp.path());
+        cmd_add.args(["add", "Write report"]).assert().success();
+        let folder = fs::read_dir(&store)?
+                .filter_map(Result::ok)
+                .find(|e| e.path().is_dir())
+                .ok_or("task was not created")?
+                .path();
+        let id = folder
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or("task has no folder name")?;
+        let mut cmd_status = tafsk(tmp.path());
+        cmd_status
+                .args(["-f", "{status}"])
+                .assert()
+                .success()
+                .stdout("todo\n");
+
+        let mut cmd_skip = tafsk(tmp.path());
+        cmd_skip.args(["status", id, "review"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                        "can't move from todo to review. Expected one of: doing",
+                ));
+
+        let mut cmd_doing = tafsk(tmp.path());
+        cmd_doing
+                .args(["status", id, "DOING"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("from todo to doing"));
+
+        let mut cmd_list = tafsk(tmp.path());
+        cmd_list.assert()
+                .success()
+                .stdout(predicate::str::contains("[STATUS: DOING] Write report"));
+
+        let mut cmd_reopen = tafsk(tmp.path());
+        cmd_reopen
+                .args(["reopen", id])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("is not closed, it is doing"));
+
+        let mut cmd_done = tafsk(tmp.path());
+        cmd_done.args(["done", id]).assert().success();
+        let mut cmd_hidden = tafsk(tmp.path());
+        cmd_hidden
+                .args(["-f", "{status}"])
+                .assert()
+                .success()
+                .stdout("");
+
+        let mut cmd_unknown = tafsk(tmp.path());
+        cmd_unknown
+                .args(["status", id, "blocked"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("Unknown state 'blocked'"));
+
+        // A hand-typed state is reported rather than crashing the listing
+        let task_file = folder.join("TASK.md");
+        let content = fs::read_to_string(&task_file)?.replace("status: done", "status: wip");
+        fs::write(&task_file, content)?;
+
+        let mut cmd_typo = tafsk(tmp.path());
+        cmd_typo.args(["-f", "{status}"])
+                .assert()
+                .success()
+                .stdout("wip\n")
+                .stderr(predicate::str::contains("Unknown state 'wip'"));
+
+        let mut cmd_guess = tafsk(tmp.path());
+        cmd_guess
+                .args(["status", id, "review"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                        "is in wip, which is not part of the workflow. Move it to todo or use --force",
+                ));
+
+        let mut cmd_fix = tafsk(tmp.path());
+        cmd_fix.args(["status", "--force", id, "review"])
+                .assert()
+                .success();
+        assert!(fs::read_to_string(&task_file)?.contains("status: review\n"));
+
+        let content_typo = fs::read_to_string(&task_file)?.replace("status: review", "status: wip");
+        fs::write(&task_file, content_typo)?;
+        let mut cmd_restart = tafsk(tmp.path());
+        cmd_restart.args(["status", id, "todo"]).assert().success();
+        assert!(fs::read_to_string(&task_file)?.contains("status: todo\n"));
+
+        Ok(())
+}
+
+#[test]
+fn should_migrate_legacy_config_and_apply_settings() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+        fs::create_dir(&store)?;
+        let config = store.join(".config");
+        fs::write(
+                &config,
+                "# Configuration for tafsk store\n-03:00\nsort = title\n",
+        )?;
+
+        // Reading the store leaves the file alone
+        tafsk(tmp.path()).args(["list"]).assert().success();
+        tafsk(tmp.path())
+                .args(["config", "get", "timezone"])
+                .assert()
+                .success()
+                .stdout("-03:00\n");
+        assert_eq!(
+                fs::read_to_string(&config)?,
+                "# Configuration for tafsk store\n-03:00\nsort = title\n"
+        );
+
+        let mut cmd_add = tafsk(tmp.path());
+        cmd_add.args(["add", "Water the plants"]).assert().success();
+
+        let migrated = fs::read_to_string(&config)?;
+        assert!(migrated.contains("timezone = \"-03:00\""), "{}", migrated);
+        assert!(migrated.contains("sort = title"), "{}", migrated);
+        assert!(!migrated.lines().any(|l| l == "-03:00"), "{}", migrated);
+
+        let mut cmd_created = tafsk(tmp.path());
+        cmd_created
+                .args(["-f", "{created}"])
+                .assert()
+                .success()
+                .stdout(predicate::str::ends_with("-03:00\n"));
+
+        fs::write(
+                &config,
+                migrated + "default_priority = 42\nid_format = \"huid\"\neditor = \"true\"\n",
+        )?;
+
+        let mut cmd_default = tafsk(tmp.path());
+        cmd_default.args(["add", "Feed the cat"]).assert().success();
+        let huids = tafsk(tmp.path()).args(["-f", "{huid}"]).output()?;
+        let first = String::from_utf8(huids.stdout)?
+                .lines()
+                .next()
+                .ok_or("no task listed")?
+                .to_string();
+        let mut cmd_edit = tafsk(tmp.path());
+        cmd_edit.env("EDITOR", "false")
+                .args(["edit", &first])
+                .assert()
+                .success();
+
+        let mut cmd_list = tafsk(tmp.path());
+        cmd_list
+                .args(["-f", "{id} {priority} {title}"])
+                .assert()
+                .success()
+                .stdout(predicate::str::is_match(
+                        r"^\d{8}-\d{6}\.[0-9a-f]{8} 42 Feed the cat\n\d{8}-\d{6}\.[0-9a-f]{8} 10 Water the plants\n$",
+                )?);
+
+        let mut cmd_init = tafsk(tmp.path());
+        cmd_init.args(["init", "+01:00"]).assert().success();
+        let updated = fs::read_to_string(&config)?;
+        assert!(updated.contains("timezone = \"+01:00\""), "{}", updated);
+        assert!(updated.contains("default_priority = 42"), "{}", updated);
+
+        fs::write(
+                &config,
+                updated.replace("id_format = \"huid\"", "id_format = short"),
+        )?;
+        let mut cmd_invalid = tafsk(tmp.path());
+        cmd_invalid
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                        "Invalid id_format in config: 'short'. Expected hash or huid",
+                ));
+
+        Ok(())
+}
+
+#[test]
+fn should_layer_user_configuration_under_the_store() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let xdg = tmp.path().join("xdg");
+        let global = tmp.path().join("global");
+        let project = tmp.path().join("project");
+        fs::create_dir(&project)?;
+
+        let with_user_config = |dir: &Path| {
+                let mut cmd = tafsk(dir);
+                cmd.env("XDG_CONFIG_HOME", &xdg).env("EDITOR", "vi");
+                cmd
+        };
+
+        with_user_config(tmp.path())
+                .args(["config", "set", "--user", "store"])
+                .arg(&global)
+                .assert()
+                .success();
+        with_user_config(tmp.path())
+                .args(["config", "set", "--user", "default_priority", "30"])
+                .assert()
+                .success();
+        with_user_config(tmp.path())
+                .args(["config", "set", "--user", "format", "{priority} {title}"])
+                .assert()
+                .success();
+        with_user_config(tmp.path())
+                .args(["config", "set", "--user", "id_format", "short"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                        "Invalid id_format: 'short'. Expected hash or huid",
+                ));
+
+        // Without a local store, the user's default store is used
+        with_user_config(tmp.path())
+                .args(["add", "Renew passport"])
+                .assert()
+                .success();
+        assert!(global.join(".config").exists());
+
+        with_user_config(&project)
+                .args(["init", "+00:00"])
+                .assert()
+                .success();
+        with_user_config(&project)
+                .args(["config", "set", "default_priority", "5"])
+                .assert()
+                .success();
+        with_user_config(&project)
+                .args(["add", "Write tests"])
+                .assert()
+                .success();
+        with_user_config(&project)
+                .args(["add", "-p", "99", "Fix bug"])
+                .assert()
+                .success();
+
+        with_user_config(&project)
+                .assert()
+                .success()
+                .stdout("99 Fix bug\n5 Write tests\n");
+        with_user_config(&project)
+                .args(["-g"])
+                .assert()
+                .success()
+                .stdout("30 Renew passport\n");
+        with_user_config(&project)
+                .args(["-f", "{title}"])
+                .assert()
+                .success()
+                .stdout("Fix bug\nWrite tests\n");
+
+        with_user_config(&project)
+                .args(["config", "get", "default_priority"])
+                .assert()
+                .success()
+                .stdout("5\n");
+        with_user_config(&project)
+                .args(["config", "get", "--user", "default_priority"])
+                .assert()
+                .success()
+                .stdout("30\n");
+        with_user_config(&project)
+                .args(["config", "get", "--store", "format"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("Setting 'format' is not set"));
+        with_user_config(&project)
+                .args(["config", "list"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                        "default_priority = \"5\"  # store\n",
+                ))
+                .stdout(predicate::str::contains("editor = \"vi\"  # environment\n"))
+                .stdout(predicate::str::contains(
+                        "format = \"{priority} {title}\"  # user\n",
+                ))
+                .stdout(predicate::str::contains(
+                        "sort = \"-priority\"  # default\n",
+                ));
+
+        Ok(())
+}
+
+#[test]
+fn should_nest_subtasks_and_count_their_completion() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let store = tmp.path().join("tasks");
+
+        tafsk(tmp.path())
+                .args(["init", "+00:00"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "-p", "50", "Release"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "Unrelated"])
+                .assert()
+                .success();
+        let release = field_of(tmp.path(), "id", "Release")?;
+
+        tafsk(tmp.path())
+                .args(["add", "--parent", &release, "Write changelog"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "--parent", &release, "-p", "30", "Tag"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(&release));
+        let tag = field_of(tmp.path(), "id", "Tag")?;
+        tafsk(tmp.path())
+                .args(["add", "--parent", &tag, "Push tag"])
+                .assert()
+                .success();
+
+        let release_dir = fs::read_dir(&store)?
+                .filter_map(Result::ok)
+                .find(|e| e.file_name().to_string_lossy().ends_with(&release))
+                .ok_or("parent directory is missing")?
+                .path();
+        assert_eq!(
+                fs::read_dir(&release_dir)?
+                        .filter_map(Result::ok)
+                        .filter(|e| e.path().join("TASK.md").exists())
+                        .count(),
+                2
+        );
+
+        tafsk(tmp.path())
+                .args(["done", &field_of(tmp.path(), "id", "Write changelog")?])
+                .assert()
+                .success();
+
+        tafsk(tmp.path())
+                .args(["--tree", "-f", "{title} {progress}"])
+                .assert()
+                .success()
+                .stdout("Release 1/2\n  Tag 0/1\n    Push tag\nUnrelated\n");
+        tafsk(tmp.path())
+                .args(["-f", "{title}", "--sort", "-priority,title"])
+                .assert()
+                .success()
+                .stdout("Release\nTag\nPush tag\nUnrelated\n");
+        tafsk(tmp.path())
+                .arg("--tree")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("[PRIORITY:  50] [1/2] Release\n"));
+        tafsk(tmp.path())
+                .args(["-f", "{parent}", "-q", "title:\"Push tag\""])
+                .assert()
+                .success()
+                .stdout(predicate::str::ends_with(format!("{}\n", tag)));
+
+        // With its parent closed, a subtask is shown under its grandparent
+        tafsk(tmp.path()).args(["done", &tag]).assert().success();
+        tafsk(tmp.path())
+                .args(["--tree", "-f", "{title}", "--sort", "-priority,title"])
+                .assert()
+                .success()
+                .stdout("Release\n  Push tag\nUnrelated\n");
+
+        Ok(())
+}
+
+#[test]
+fn should_move_tasks_between_parents_and_stores() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let local = tmp.path().join("tasks");
+        let global = tmp.path().join("global");
+
+        let with_global = || {
+                let mut cmd = tafsk(tmp.path());
+                cmd.env("TAFSK_STORE_DIR", &global);
+                cmd
+        };
+        let folder_of = |store: &Path| -> Result<String, Box<dyn Error>> {
+                Ok(fs::read_dir(store)?
+                        .filter_map(Result::ok)
+                        .find(|e| e.path().join("TASK.md").exists())
+                        .ok_or("no task in store")?
+                        .file_name()
+                        .to_string_lossy()
+                        .into_owned())
+        };
+
+        with_global().args(["init", "+00:00"]).assert().success();
+        with_global().args(["add", "Plan trip"]).assert().success();
+        let trip = folder_of(&local)?;
+        fs::write(local.join(&trip).join("notes.txt"), "Pack light")?;
+
+        with_global()
+                .args(["mv", &trip, "--to-global"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(format!(
+                        "to {}",
+                        global.join(&trip).join("TASK.md").display()
+                )));
+        assert!(!local.join(&trip).exists());
+        assert_eq!(
+                fs::read_to_string(global.join(&trip).join("notes.txt"))?,
+                "Pack light"
+        );
+
+        with_global()
+                .args(["add", "Book flights"])
+                .assert()
+                .success();
+        let flights = folder_of(&local)?;
+        with_global()
+                .args(["mv", &flights, "--to-global", "--parent", &trip])
+                .assert()
+                .success();
+        assert!(global.join(&trip).join(&flights).join("TASK.md").exists());
+
+        with_global()
+                .args(["mv", "-g", &trip, "--parent", &flights])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("under itself"));
+
+        with_global()
+                .args(["mv", "-g", &flights, "--parent", &trip])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("is already at"));
+
+        // A task with the same HUID is never overwritten
+        fs::create_dir(local.join(&trip))?;
+        fs::write(
+                local.join(&trip).join("TASK.md"),
+                "---\nstatus: OPEN\npriority: 10\ntags: []\n---\n\n# Copy\n",
+        )?;
+        with_global()
+                .args(["mv", "-g", &trip, "--to-local"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("a task with the same HUID exists"));
+        fs::remove_dir_all(local.join(&trip))?;
+
+        with_global()
+                .args(["mv", "-g", &trip, "--to-local"])
+                .assert()
+                .success();
+        assert!(local.join(&trip).join(&flights).join("TASK.md").exists());
+        assert!(local.join(&trip).join("notes.txt").exists());
+
+        with_global()
+                .args(["mv", &flights, "--to-local"])
+                .assert()
+                .success();
+        assert!(local.join(&flights).join("TASK.md").exists());
+        assert!(!local.join(&trip).join(&flights).exists());
+
+        // Nor duplicated elsewhere in the target store, under another parent
+        let nested = global.join("20250101-000000.00000000").join(&flights);
+        fs::create_dir_all(&nested)?;
+        for dir in [nested.as_path(), nested.parent().ok_or("no parent")?] {
+                fs::write(
+                        dir.join("TASK.md"),
+                        "---\nstatus: OPEN\npriority: 10\ntags: []\n---\n\n# Copy\n",
+                )?;
+        }
+        with_global()
+                .args(["mv", &flights, "--to-global"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(format!(
+                        "a task with the same HUID exists at {}",
+                        nested.display()
+                )));
+        assert!(!global.join(&flights).exists());
+        assert!(local.join(&flights).join("TASK.md").exists());
+
+        Ok(())
+}
+
+#[test]
+fn should_track_dependencies_and_refuse_cycles() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+
+        tafsk(tmp.path())
+                .args(["init", "+00:00"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "-p", "30", "Deploy"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "-p", "20", "Test"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "-p", "10", "Build"])
+                .assert()
+                .success();
+        let (deploy, test, build) = (
+                field_of(tmp.path(), "id", "Deploy")?,
+                field_of(tmp.path(), "id", "Test")?,
+                field_of(tmp.path(), "id", "Build")?,
+        );
+
+        tafsk(tmp.path())
+                .args(["depend", &deploy, "--on", &test])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("'Deploy' now depends on"));
+        tafsk(tmp.path())
+                .args(["depend", &test, "--on", &build])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["depend", &build, "--on", &deploy])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(format!(
+                        "it would close the cycle {} -> {} -> {} -> {}",
+                        build, deploy, test, build
+                )));
+        tafsk(tmp.path())
+                .args(["depend", &build, "--on", &build])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("cycle"));
+
+        tafsk(tmp.path())
+                .args(["--ready", "-f", "{title}"])
+                .assert()
+                .success()
+                .stdout("Build\n");
+        tafsk(tmp.path())
+                .args(["--blocked", "-f", "{title} {blocked_by}"])
+                .assert()
+                .success()
+                .stdout(format!("Deploy {}\nTest {}\n", test, build));
+        tafsk(tmp.path())
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(format!(
+                        "[PRIORITY:  30] [BLOCKED BY: {}] Deploy",
+                        test
+                )));
+
+        tafsk(tmp.path()).args(["done", &build]).assert().success();
+        tafsk(tmp.path())
+                .args(["--ready", "-f", "{title}"])
+                .assert()
+                .success()
+                .stdout("Test\n");
+        tafsk(tmp.path())
+                .args(["-f", "jsonl", "-q", "title:Deploy"])
+                .assert()
+                .success()
+                .stdout(predicate::str::is_match(
+                        r#""depends":\["\d{8}-\d{6}\.[0-9a-f]{8}"\]"#,
+                )?);
+
+        Ok(())
+}
+
+#[test]
+fn should_export_tasks_and_links_as_dot() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+
+        tafsk(tmp.path())
+                .args(["init", "+00:00"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "-p", "50", "Release \"2.0\"", "+work"])
+                .assert()
+                .success();
+        let release = field_of(tmp.path(), "huid", "Release \"2.0\"")?;
+        tafsk(tmp.path())
+                .args(["add", "--parent", &release, "Docs", "+work"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "Build", "+work"])
+                .assert()
+                .success();
+        tafsk(tmp.path())
+                .args(["add", "Groceries", "+home"])
+                .assert()
+                .success();
+        let (docs, build) = (
+                field_of(tmp.path(), "huid", "Docs")?,
+                field_of(tmp.path(), "huid", "Build")?,
+        );
+        tafsk(tmp.path())
+                .args(["depend", &release, "--on", &build])
+                .assert()
+                .success();
+        tafsk(tmp.path()).args(["done", &docs]).assert().success();
+
+        let output = tafsk(tmp.path()).args(["graph", "-c", "+work"]).output()?;
+        assert!(output.status.success());
+        let dot = String::from_utf8(output.stdout)?;
+
+        assert!(dot.starts_with("digraph tasks {\n"), "{}", dot);
+        assert!(dot.ends_with("}\n"), "{}", dot);
+        assert!(
+                dot.contains(&format!(
+                        "\"{}\" [label=\"Release \\\"2.0\\\"\\n{}  P50  OPEN\", fillcolor=\"#ffffff\"];",
+                        release,
+                        &release[16..]
+                )),
+                "{}",
+                dot
+        );
+        assert!(dot.contains("CLOSED\", fillcolor=\"#c8e6c9\"];"), "{}", dot);
+        assert!(
+                dot.contains(&format!(
+                        "\"{}\" -> \"{}\" [style=dashed, color=\"gray50\"];",
+                        release, docs
+                )),
+                "{}",
+                dot
+        );
+        assert!(
+                dot.contains(&format!("\"{}\" -> \"{}\";", build, release)),
+                "{}",
+                dot
+        );
+        assert!(!dot.contains("Groceries"), "{}", dot);
+
+        // Links to tasks that are left out aren't drawn
+        let blocked_output = tafsk(tmp.path()).args(["graph", "--blocked"]).output()?;
+        let blocked_dot = String::from_utf8(blocked_output.stdout)?;
+        assert!(blocked_dot.contains("Release"), "{}", blocked_dot);
+        assert!(!blocked_dot.contains("->"), "{}", blocked_dot);
+
+        Ok(())
+}
+
+#[test]
+fn should_lock_the_store_while_changing_it() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+        let lock = tmp.path().join("tasks").join(".lock");
+
+        let impatient = || {
+                let mut cmd = tafsk(tmp.path());
+                cmd.env("TAFSK_LOCK_TIMEOUT", "0.2");
+                cmd
+        };
+
+        impatient().args(["init", "+00:00"]).assert().success();
+        impatient().args(["add", "Locked"]).assert().success();
+        let output = impatient().args(["-f", "{id}"]).output()?;
+        let id = String::from_utf8(output.stdout)?.trim().to_string();
+
+        // Held by a running process, this one
+        let held = fs::File::create(&lock)?;
+        held.lock()?;
+        fs::write(&lock, format!("{}\n", std::process::id()))?;
+        impatient()
+                .args(["done", &id])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(format!(
+                        "is locked by process {}",
+                        std::process::id()
+                )));
+        impatient()
+                .args(["add", "Waiting"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("is locked by process"));
+        // The editor may run, but its result is checked under the lock
+        impatient()
+                .args(["edit", &id])
+                .env("EDITOR", "true")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("is locked by process"));
+
+        // Reading doesn't need the lock
+        impatient()
+                .args(["-f", "{title} {status}"])
+                .assert()
+                .success()
+                .stdout("Locked OPEN\n");
+
+        // Released along with the file, as when its holder dies, even though
+        // the ID of the last holder is still written in it
+        drop(held);
+        impatient().args(["done", &id]).assert().success();
+
+        impatient()
+                .args(["done", &id])
+                .env("TAFSK_LOCK_TIMEOUT", "soon")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                        "Invalid TAFSK_LOCK_TIMEOUT 'soon'",
+                ));
+
+        Ok(())
+}
+
+#[test]
+fn should_keep_every_change_of_concurrent_commands() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+
+        tafsk(tmp.path())
+                .args(["init", "+00:00"])
+                .assert()
+                .success();
+        tafsk(tmp.path()).args(["add", "Shared"]).assert().success();
+        let output = tafsk(tmp.path()).args(["-f", "{id}"]).output()?;
+        let id = String::from_utf8(output.stdout)?.trim().to_string();
+
+        // Each one rewrites the same task, and would drop the tags of the others
+        // if they read it at the same time
+        let children = (0..8)
+                .map(|i| {
+                        tafsk_process(tmp.path())
+                                .args(["modify", &id, &format!("+tag{}", i)])
+                                .stdout(std::process::Stdio::null())
+                                .spawn()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        for mut child in children {
+                assert!(child.wait()?.success());
+        }
+
+        let tags_output = tafsk(tmp.path()).args(["-f", "{tags}"]).output()?;
+        let tags = String::from_utf8(tags_output.stdout)?;
+        for i in 0..8 {
+                assert!(tags.contains(&format!("tag{}", i)), "{}", tags);
+        }
+
+        Ok(())
+}
+
+#[test]
+fn should_keep_every_task_of_concurrent_identical_adds() -> Result<(), Box<dyn Error>> {
+        let tmp = tempfile::tempdir()?;
+
+        tafsk(tmp.path())
+                .args(["init", "+00:00"])
+                .assert()
+                .success();
+
+        // Same content in the same second gives the same HUID at first
+        let children = (0..16)
+                .map(|_| {
+                        tafsk_process(tmp.path())
+                                .args(["add", "Same title", "+same"])
+                                .stdout(std::process::Stdio::null())
+                                .spawn()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        for mut child in children {
+                assert!(child.wait()?.success());
+        }
+
+        let output = tafsk(tmp.path()).args(["-f", "{huid} {title}"]).output()?;
+        let listed = String::from_utf8(output.stdout)?;
+        let mut huids: Vec<&str> = listed
+                .lines()
+                .filter_map(|line| line.strip_suffix(" Same title"))
+                .collect();
+        assert_eq!(huids.len(), 16, "{}", listed);
+        huids.sort();
+        huids.dedup();
+        assert_eq!(huids.len(), 16, "{}", listed);
+
+        Ok(())
+}